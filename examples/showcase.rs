@@ -1,7 +1,7 @@
 //! Showcases clap parsing and different widgets
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 use clap::{Parser, ValueHint};
-use klask::Settings;
+use klask::{Settings, Theme};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -16,6 +16,10 @@ pub struct Showcase {
     field_with_default: String,
     #[arg(long)]
     flag: bool,
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=10), default_value_t = 5)]
+    number_in_range: u8,
+    #[arg(long)]
+    decimal_number: Option<f64>,
     #[arg(short, long, action = clap::ArgAction::Count)]
     count_occurrences_as_a_nice_counter: u8,
     #[clap(subcommand)]
@@ -59,11 +63,10 @@ pub enum InnerInnerSubcommand {
 }
 
 fn main() {
+    let mut settings = Settings::default();
+    settings.themes = vec![Theme::dark(), Theme::light(), Theme::high_contrast()];
     #[cfg(not(target_arch = "wasm32"))]
-    klask::run_derived_native::<Showcase, _>(Settings::default(), |o| println!("{o:#?}"));
+    klask::run_derived_native::<Showcase, _>(settings, |o| println!("{o:#?}"));
     #[cfg(target_arch = "wasm32")]
-    klask::run_derived_web::<Showcase, _>(
-        Settings::default(),
-        |o| async move { println!("{o:#?}") },
-    );
+    klask::run_derived_web::<Showcase, _>(settings, |o| async move { println!("{o:#?}") });
 }
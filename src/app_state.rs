@@ -1,7 +1,16 @@
-use crate::{arg_state::ArgState, settings::Localization};
-use clap::Command;
+use crate::{
+    arg_state::{ArgKind, ArgState},
+    error::{clap_message, displayed_arg_ids, involved_args},
+    preset::Preset,
+    settings::Localization,
+    to_sentence_case,
+};
+use clap::{
+    error::{ContextKind, ContextValue, ErrorKind},
+    ArgMatches, Command,
+};
 use eframe::egui::{widgets::Widget, Grid, Response, Ui};
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, ffi::OsString};
 use uuid::Uuid;
 
 /// App state
@@ -10,8 +19,25 @@ pub struct AppState<'s> {
     id: Uuid,
     about: Option<String>,
     args: Vec<ArgState<'s>>,
+    /// Groups whose args can't be used together. They are displayed as a single choice.
+    groups: Vec<ExclusiveGroup>,
     subcommands: BTreeMap<String, AppState<'s>>,
     current: Option<String>,
+    /// Built [`Command`] of this level. Used to check relations between args.
+    cmd: Command,
+    /// Arguments last used to check relations, so the check only reruns on changes.
+    checked_args: Option<Vec<String>>,
+    localization: &'s Localization,
+}
+
+/// [`clap::ArgGroup`] with `multiple(false)`.
+#[derive(Debug, Clone)]
+struct ExclusiveGroup {
+    name: String,
+    required: bool,
+    /// Indices into [`AppState::args`]
+    args: Vec<usize>,
+    selected: Option<usize>,
 }
 
 impl<'s> AppState<'s> {
@@ -27,10 +53,41 @@ impl<'s> AppState<'s> {
                 .or_else(|| app.get_long_about().map(|v| v.to_string()))
         };
 
-        let args = app
+        // Building fills in what clap displays in errors and resolves groups
+        let mut cmd = app.clone();
+        cmd.build();
+
+        let mut args: Vec<_> = cmd
             .get_arguments()
             .filter(|a| a.get_id() != "help" && a.get_id() != "version")
-            .map(|a| ArgState::new(a, localization))
+            .map(|a| ArgState::new(a, &cmd, localization))
+            .collect();
+
+        // Conflicts go both ways
+        for i in 0..args.len() {
+            for j in 0..args.len() {
+                if args[j].conflicts.contains(&args[i].id)
+                    && !args[i].conflicts.contains(&args[j].id)
+                {
+                    let id = args[j].id.clone();
+                    args[i].conflicts.push(id);
+                }
+            }
+        }
+
+        let groups = cmd
+            .get_groups()
+            .filter(|&g| !g.clone().is_multiple())
+            .map(|g| ExclusiveGroup {
+                name: to_sentence_case(g.get_id().as_ref()),
+                required: g.is_required_set(),
+                args: g
+                    .get_args()
+                    .filter_map(|id| args.iter().position(|a| a.id == id.as_str()))
+                    .collect(),
+                selected: None,
+            })
+            .filter(|g| !g.args.is_empty())
             .collect();
 
         let subcommands = app
@@ -47,25 +104,233 @@ impl<'s> AppState<'s> {
             id: Uuid::new_v4(),
             about,
             args,
+            groups,
             subcommands,
             current: app
                 .get_subcommands()
                 .map(|app| app.get_name().to_string())
                 .next(),
+            cmd,
+            checked_args: None,
+            localization,
+        }
+    }
+
+    /// Grey out args that conflict with filled ones and recompute which args are required
+    /// because of `requires`, `required_unless_present`, `required_if_eq` etc.
+    /// Only this level is updated, subcommands are updated when they are displayed.
+    fn update_relations(&mut self) {
+        let set: Vec<String> = self
+            .args
+            .iter()
+            .filter(|a| a.is_set())
+            .map(|a| a.id.clone())
+            .collect();
+        for arg in &mut self.args {
+            arg.disabled = !arg.is_set() && arg.conflicts.iter().any(|c| set.contains(c));
+        }
+
+        for group in &mut self.groups {
+            if !matches!(group.selected, Some(i) if self.args[i].is_set()) {
+                if let Some(&i) = group.args.iter().find(|&&i| self.args[i].is_set()) {
+                    group.selected = Some(i);
+                }
+            }
+        }
+
+        let mut args = vec![];
+        for arg in &self.args {
+            args = match arg.get_partial_cmd_args(args.clone()) {
+                Ok(args) => args,
+                Err(_) => args,
+            };
+        }
+        if self.checked_args.as_ref() == Some(&args) {
+            return;
+        }
+
+        // Subcommands are checked separately
+        let result = self
+            .cmd
+            .clone()
+            .no_binary_name(true)
+            .subcommand_required(false)
+            .arg_required_else_help(false)
+            .try_get_matches_from(&args);
+        let missing = match &result {
+            Ok(_) => vec![],
+            Err(err) if err.kind() == ErrorKind::MissingRequiredArgument => {
+                match err.get(ContextKind::InvalidArg) {
+                    Some(ContextValue::Strings(missing)) => missing.clone(),
+                    Some(ContextValue::String(missing)) => vec![missing.clone()],
+                    _ => vec![],
+                }
+            }
+            // Relations can't be determined until other errors are fixed
+            Err(_) => {
+                self.checked_args = Some(args);
+                return;
+            }
+        };
+        let missing = displayed_arg_ids(&self.cmd, &missing);
+        for arg in &mut self.args {
+            arg.relation_required = missing.contains(&arg.id);
+        }
+        self.checked_args = Some(args);
+    }
+
+    /// Fill the form from command line arguments, without the binary name.
+    /// Arguments clap doesn't recognise are skipped and returned.
+    pub fn set_from_args(&mut self, args: &[OsString]) -> Vec<OsString> {
+        let cmd = self.cmd.clone().no_binary_name(true);
+        let mut args = args.to_vec();
+        let mut unplaced = vec![];
+
+        let matches = loop {
+            let err = match cmd.clone().try_get_matches_from(&args) {
+                Ok(matches) => break matches,
+                Err(err) => err,
+            };
+
+            let token = match (err.kind(), err.get(ContextKind::InvalidArg)) {
+                (ErrorKind::UnknownArgument, Some(ContextValue::String(token))) => Some(token),
+                (ErrorKind::InvalidSubcommand, _) => {
+                    match err.get(ContextKind::InvalidSubcommand) {
+                        Some(ContextValue::String(token)) => Some(token),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            // Clap reports `--unknown=value` as `--unknown`
+            let position = token.and_then(|token| {
+                args.iter().position(|arg| {
+                    arg.to_str().is_some_and(|arg| {
+                        arg == token
+                            || arg
+                                .strip_prefix(token.as_str())
+                                .is_some_and(|rest| rest.starts_with('='))
+                    })
+                })
+            });
+
+            match position {
+                Some(position) => unplaced.push(args.remove(position)),
+                // Other errors will be shown in the form, so fill in as much as possible
+                None => {
+                    break cmd
+                        .clone()
+                        .ignore_errors(true)
+                        .try_get_matches_from(&args)
+                        .unwrap_or_default()
+                }
+            }
+        };
+
+        self.set_from_matches(&matches);
+        unplaced
+    }
+
+    /// Fill the form from matches of this state's [`Command`].
+    /// Args that weren't passed on the command line are reset.
+    pub fn set_from_matches(&mut self, matches: &ArgMatches) {
+        for arg in &mut self.args {
+            arg.set_from_matches(matches);
+        }
+
+        match matches.subcommand() {
+            Some((name, matches)) if self.subcommands.contains_key(name) => {
+                self.current = Some(name.to_string());
+                self.subcommands
+                    .get_mut(name)
+                    .unwrap()
+                    .set_from_matches(matches);
+            }
+            _ => {
+                if let Some(current) = &self.current {
+                    self.subcommands
+                        .get_mut(current)
+                        .unwrap()
+                        .set_from_matches(&ArgMatches::default());
+                }
+            }
         }
     }
 
+    /// Store the values of the args and the selected subcommands in `preset`.
+    pub fn save_preset(&self, preset: &mut Preset) {
+        self.save_preset_at("", preset);
+    }
+
+    fn save_preset_at(&self, path: &str, preset: &mut Preset) {
+        for arg in &self.args {
+            if let Some(value) = arg.preset_value() {
+                preset.args.insert(format!("{path}{}", arg.id), value);
+            }
+        }
+
+        if let Some(current) = &self.current {
+            preset.subcommands.push(current.clone());
+            self.subcommands[current].save_preset_at(&format!("{path}{current}/"), preset);
+        }
+    }
+
+    /// Fill the form from `preset`. Args missing from it are reset.
+    pub fn load_preset(&mut self, preset: &Preset) {
+        self.load_preset_at("", &preset.subcommands, preset);
+    }
+
+    fn load_preset_at(&mut self, path: &str, subcommands: &[String], preset: &Preset) {
+        for arg in &mut self.args {
+            arg.set_preset_value(preset.args.get(&format!("{path}{}", arg.id)));
+        }
+
+        if let Some(name) = subcommands.first() {
+            if self.subcommands.contains_key(name) {
+                self.current = Some(name.clone());
+            }
+        }
+        if let Some(current) = &self.current {
+            let path = format!("{path}{current}/");
+            self.subcommands.get_mut(current).unwrap().load_preset_at(
+                &path,
+                subcommands.get(1..).unwrap_or_default(),
+                preset,
+            );
+        }
+    }
+
+    /// Highlight the args a clap error is about with the error's message.
+    /// Pass `None` to clear errors from a previous run.
+    pub fn update_validation_error(&mut self, err: Option<&clap::Error>) {
+        let (ids, message) = match err {
+            Some(err) => (self.error_arg_ids(&involved_args(err)), clap_message(err)),
+            None => (vec![], String::new()),
+        };
+        self.set_validation_error(&ids, &message);
+    }
+
+    /// Ids of args in the selected subcommands that clap displays as one of `displayed`.
+    fn error_arg_ids(&self, displayed: &[String]) -> Vec<String> {
+        let mut ids = displayed_arg_ids(&self.cmd, displayed);
+
+        if let Some(current) = &self.current {
+            ids.extend(self.subcommands[current].error_arg_ids(displayed));
+        }
+        ids
+    }
+
     /// Apply [`ArgState<'_>::update_validation_error`] to self args recursively.
-    pub fn update_validation_error(&mut self, name: &str, message: &str) {
+    fn set_validation_error(&mut self, ids: &[String], message: &str) {
         for arg in &mut self.args {
-            arg.update_validation_error(name, message);
+            arg.update_validation_error(ids, message);
         }
 
         if let Some(current) = &self.current {
             self.subcommands
                 .get_mut(current)
                 .unwrap()
-                .update_validation_error(name, message);
+                .set_validation_error(ids, message);
         }
     }
 
@@ -82,10 +347,43 @@ impl<'s> AppState<'s> {
             Ok(args)
         }
     }
+
+    /// Like [`AppState::get_cmd_args`] but skips missing required values, so it works for incomplete forms.
+    pub fn get_partial_cmd_args(&self, mut args: Vec<String>) -> Result<Vec<String>, String> {
+        for arg in &self.args {
+            args = arg.get_partial_cmd_args(args)?;
+        }
+
+        if let Some(current) = &self.current {
+            args.push(current.clone());
+            self.subcommands[current].get_partial_cmd_args(args)
+        } else {
+            Ok(args)
+        }
+    }
+}
+
+impl AppState<'_> {
+    /// Select `selected` in the group at `group`, clearing the other args of the group.
+    /// A flag has no value to enter, so selecting it sets it.
+    fn select_in_group(&mut self, group: usize, selected: Option<usize>) {
+        let group = &mut self.groups[group];
+        group.selected = selected;
+        for &j in &group.args {
+            if Some(j) != selected {
+                self.args[j].reset();
+            }
+        }
+        if let Some(ArgKind::Bool(bool)) = selected.map(|j| &mut self.args[j].kind) {
+            *bool = true;
+        }
+    }
 }
 
 impl Widget for &mut AppState<'_> {
     fn ui(self, ui: &mut Ui) -> Response {
+        self.update_relations();
+
         ui.vertical(|ui| {
             if let Some(ref about) = self.about {
                 ui.label(about);
@@ -97,9 +395,44 @@ impl Widget for &mut AppState<'_> {
                     .num_columns(2)
                     .striped(true)
                     .show(ui, |ui| {
-                        for arg in &mut self.args {
-                            ui.add(arg);
+                        for i in 0..self.args.len() {
+                            let Some(g) = self.groups.iter().position(|g| g.args.contains(&i))
+                            else {
+                                ui.add(&mut self.args[i]);
+                                ui.end_row();
+                                continue;
+                            };
+
+                            // The whole group is displayed in place of its first arg
+                            let group = &self.groups[g];
+                            if group.args[0] != i {
+                                continue;
+                            }
+
+                            ui.label(&group.name);
+                            let mut selected = group.selected;
+                            ui.horizontal_wrapped(|ui| {
+                                if !group.required {
+                                    ui.radio_value(
+                                        &mut selected,
+                                        None,
+                                        &self.localization.no_selection,
+                                    );
+                                }
+                                for &j in &group.args {
+                                    ui.radio_value(&mut selected, Some(j), &self.args[j].name);
+                                }
+                            });
                             ui.end_row();
+
+                            if selected != group.selected {
+                                self.select_in_group(g, selected);
+                            }
+
+                            if let Some(j) = selected {
+                                ui.add(&mut self.args[j]);
+                                ui.end_row();
+                            }
                         }
                     });
             }
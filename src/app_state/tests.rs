@@ -1,6 +1,7 @@
 use super::AppState;
 use crate::{
     arg_state::{ArgKind, ArgState},
+    preset::{Preset, PresetValue},
     settings::Localization,
 };
 use clap::builder::NonEmptyStringValueParser;
@@ -200,9 +201,372 @@ fn multiple_occurrences() {
     )
 }
 
+#[derive(Debug, Parser, PartialEq, Eq)]
+struct Relations {
+    #[arg(long, conflicts_with = "b")]
+    a: Option<String>,
+    #[arg(long)]
+    b: Option<String>,
+    #[arg(long, required_unless_present = "b")]
+    c: Option<String>,
+    #[arg(long, requires = "a")]
+    d: bool,
+}
+
+#[test]
+fn relations() {
+    let app = Relations::command();
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+
+    app_state.update_relations();
+    assert!(app_state.args[2].relation_required);
+    assert!(app_state.get_cmd_args(vec![]).is_err());
+    assert!(!app_state.args[0].disabled && !app_state.args[1].disabled);
+
+    app_state.args[3].set(true);
+    app_state.update_relations();
+    assert!(app_state.args[0].relation_required);
+
+    app_state.args[1].enter("b");
+    app_state.update_relations();
+    assert!(!app_state.args[2].relation_required);
+    assert!(app_state.args[0].disabled);
+    assert!(!app_state.args[1].disabled);
+}
+
+#[test]
+fn errors_mapped_to_fields() {
+    let mut app = Relations::command().no_binary_name(true);
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+
+    app_state.args[0].enter("a");
+    app_state.args[1].enter("b");
+    let err = app
+        .try_get_matches_from_mut(app_state.get_cmd_args(vec![]).unwrap())
+        .unwrap_err();
+    assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    app_state.update_validation_error(Some(&err));
+    let message = app_state.args[0].validation_error.clone().unwrap();
+    assert!(message.contains("cannot be used with"), "{message}");
+    assert_eq!(app_state.args[1].validation_error, Some(message));
+    assert_eq!(app_state.args[2].validation_error, None);
+
+    app_state.update_validation_error(None);
+    assert!(app_state.args.iter().all(|a| a.validation_error.is_none()));
+}
+
+#[derive(Debug, Parser, PartialEq, Eq)]
+#[command(group(clap::ArgGroup::new("required_group").required(true)))]
+struct RequiredGroup {
+    #[arg(long, value_name = "WHO", group = "required_group")]
+    one: Option<String>,
+    #[arg(long, group = "required_group")]
+    two: bool,
+    #[arg(group = "required_group")]
+    file: Option<String>,
+}
+
+#[test]
+fn group_errors_mapped_to_fields() {
+    let mut app = RequiredGroup::command().no_binary_name(true);
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+
+    let err = app
+        .try_get_matches_from_mut(app_state.get_cmd_args(vec![]).unwrap())
+        .unwrap_err();
+    assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+    app_state.update_validation_error(Some(&err));
+    assert!(app_state.args.iter().all(|a| a.validation_error.is_some()));
+}
+
+#[derive(Debug, Parser, PartialEq, Eq)]
+#[command(group(clap::ArgGroup::new("mode").args(["fast", "slow"])))]
+struct ExclusiveGroup {
+    #[arg(long)]
+    fast: bool,
+    #[arg(long)]
+    slow: Option<String>,
+    #[arg(long)]
+    other: bool,
+}
+
+#[test]
+fn exclusive_group() {
+    let app = ExclusiveGroup::command();
+    let localization = Localization::default();
+    let app_state = AppState::new(&app, &localization, false);
+    assert_eq!(app_state.groups.len(), 1);
+    assert_eq!(app_state.groups[0].args, vec![0, 1]);
+
+    // Selecting a flag sets it
+    let mut app_state = app_state;
+    app_state.select_in_group(0, Some(0));
+    let args = app_state.get_cmd_args(vec!["_name".into()]).unwrap();
+    assert_eq!(args, ["_name", "--fast"]);
+    app_state.select_in_group(0, Some(1));
+    assert!(!app_state.args[0].is_set());
+
+    test_app(
+        |args| args[1].enter("s"),
+        ExclusiveGroup {
+            fast: false,
+            slow: Some("s".into()),
+            other: false,
+        },
+    );
+}
+
+#[derive(Debug, Parser, PartialEq)]
+struct Numbers {
+    #[arg(long, value_parser = clap::value_parser!(u8).range(5..10))]
+    slider: u8,
+    #[arg(long)]
+    int: i32,
+    #[arg(long)]
+    unsigned: Option<u64>,
+    #[arg(long, default_value_t = 1.5)]
+    float: f64,
+    #[arg(long, action = clap::ArgAction::Set)]
+    bool_value: bool,
+}
+
+#[test]
+fn numbers() {
+    let app = Numbers::command();
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+    assert!(matches!(
+        app_state.args[0].kind,
+        ArgKind::UInt {
+            value: 5,
+            enabled: false,
+            slider: true,
+            ref range
+        } if *range == (5..=9)
+    ));
+    assert!(matches!(
+        app_state.args[1].kind,
+        ArgKind::Int { slider: false, ref range, .. } if *range == (i32::MIN.into()..=i32::MAX.into())
+    ));
+    assert!(matches!(
+        app_state.args[2].kind,
+        ArgKind::UInt { enabled: false, .. }
+    ));
+    assert!(matches!(
+        app_state.args[3].kind,
+        ArgKind::Float { value, enabled: false } if value == 1.5
+    ));
+    assert!(matches!(app_state.args[4].kind, ArgKind::BoolValue { .. }));
+
+    // Required numbers without a value are missing, not 0
+    assert!(app_state.get_cmd_args(vec!["_name".into()]).is_err());
+    app_state.args[1].number(0);
+    assert!(app_state.get_cmd_args(vec!["_name".into()]).is_err());
+
+    // Values set outside the bounds of the value parser are reported
+    app_state.args[0].number(3);
+    app_state.args[0].update_value_error();
+    assert!(app_state.args[0].value_error.is_some());
+    app_state.args[0].number(5);
+    app_state.args[0].update_value_error();
+    assert_eq!(app_state.args[0].value_error, None);
+
+    let mut app = app;
+    let matches = app
+        .try_get_matches_from_mut(app_state.get_cmd_args(vec!["_name".into()]).unwrap())
+        .unwrap();
+    assert_eq!(
+        Numbers::from_arg_matches(&matches).unwrap(),
+        Numbers {
+            slider: 5,
+            int: 0,
+            unsigned: None,
+            float: 1.5,
+            bool_value: false,
+        }
+    );
+}
+
+#[test]
+fn numbers_entered() {
+    test_app(
+        |args| {
+            args[0].number(7);
+            args[1].number(-3);
+            args[2].number(12);
+            if let ArgKind::Float { value, enabled } = &mut args[3].kind {
+                *value = -0.25;
+                *enabled = true;
+            }
+            if let ArgKind::BoolValue { value, .. } = &mut args[4].kind {
+                *value = true;
+            }
+        },
+        Numbers {
+            slider: 7,
+            int: -3,
+            unsigned: Some(12),
+            float: -0.25,
+            bool_value: true,
+        },
+    );
+}
+
+fn parse_even(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(v) if v % 2 == 0 => Ok(v),
+        Ok(_) => Err("not even".into()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[derive(Debug, Parser, PartialEq, Eq)]
+struct LiveValidation {
+    #[arg(long)]
+    numbers: Vec<u32>,
+    #[arg(long, value_parser = parse_even)]
+    even: u32,
+    #[arg(long)]
+    path: PathBuf,
+}
+
+#[test]
+fn live_validation() {
+    let app = LiveValidation::command();
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+
+    app_state.args[0].enter_multiple(["1", "x"]);
+    app_state.args[1].number(3);
+    for arg in &mut app_state.args {
+        arg.update_value_error();
+    }
+    assert_eq!(
+        app_state.args[0].value_error.as_deref(),
+        Some("invalid digit found in string")
+    );
+    assert_eq!(app_state.args[1].value_error.as_deref(), Some("not even"));
+    assert_eq!(app_state.args[2].value_error, None);
+
+    app_state.args[0].enter_multiple(["1", "2"]);
+    app_state.args[1].number(4);
+    for arg in &mut app_state.args {
+        arg.update_value_error();
+    }
+    assert_eq!(app_state.args[0].value_error, None);
+    assert_eq!(app_state.args[1].value_error, None);
+}
+
+#[derive(Debug, Parser, PartialEq)]
+struct Import {
+    #[arg(long)]
+    name: String,
+    #[arg(long)]
+    files: Vec<String>,
+    #[arg(short, action = clap::ArgAction::Count)]
+    verbose: u8,
+    #[arg(long)]
+    flag: bool,
+    #[arg(long, allow_negative_numbers = true)]
+    int: Option<i32>,
+    #[arg(long)]
+    float: Option<f64>,
+}
+
+#[test]
+fn import_args() {
+    let app = Import::command();
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+    app_state.args[1].enter_multiple(["stale"]);
+
+    let args = "--name n --unknown=1 --files a --files b -vv --flag --int -4 --float 0.5";
+    let args: Vec<_> = args.split(' ').map(Into::into).collect();
+    let unplaced = app_state.set_from_args(&args);
+    assert_eq!(unplaced, ["--unknown=1"]);
+
+    let args = app_state.get_cmd_args(vec!["_name".into()]).unwrap();
+    let matches = app.try_get_matches_from(args).unwrap();
+    assert_eq!(
+        Import::from_arg_matches(&matches).unwrap(),
+        Import {
+            name: "n".into(),
+            files: vec!["a".into(), "b".into()],
+            verbose: 2,
+            flag: true,
+            int: Some(-4),
+            float: Some(0.5),
+        }
+    );
+}
+
+#[derive(Debug, Parser, PartialEq)]
+struct WithPreset {
+    #[arg(long)]
+    name: Option<String>,
+    #[command(subcommand)]
+    command: PresetCommand,
+}
+
+#[derive(Debug, clap::Subcommand, PartialEq)]
+enum PresetCommand {
+    Build {
+        #[arg(long)]
+        release: bool,
+        #[arg(long)]
+        jobs: Option<u8>,
+        #[arg(long)]
+        features: Vec<String>,
+    },
+    Clean,
+}
+
+#[test]
+fn preset_round_trip() {
+    let app = WithPreset::command();
+    let localization = Localization::default();
+    let mut app_state = AppState::new(&app, &localization, false);
+    let args = "--name n build --release --jobs 4 --features a --features b";
+    let args: Vec<_> = args.split(' ').map(Into::into).collect();
+    assert!(app_state.set_from_args(&args).is_empty());
+
+    let mut preset = Preset::new("test");
+    app_state.save_preset(&mut preset);
+    assert_eq!(preset.subcommands, ["build"]);
+    assert_eq!(preset.args["build/release"], PresetValue::Bool(true));
+
+    // Survives serialization and loads into a fresh form
+    let json = serde_json::to_string(&preset).unwrap();
+    let preset: Preset = serde_json::from_str(&json).unwrap();
+    let mut app_state = AppState::new(&app, &localization, false);
+    app_state.load_preset(&preset);
+
+    let args = app_state.get_cmd_args(vec!["_name".into()]).unwrap();
+    let matches = app.clone().try_get_matches_from(args).unwrap();
+    assert_eq!(
+        WithPreset::from_arg_matches(&matches).unwrap(),
+        WithPreset {
+            name: Some("n".into()),
+            command: PresetCommand::Build {
+                release: true,
+                jobs: Some(4),
+                features: vec!["a".into(), "b".into()],
+            },
+        }
+    );
+
+    // Missing args are reset
+    app_state.load_preset(&Preset::new("empty"));
+    let args = app_state.get_cmd_args(vec!["_name".into()]).unwrap();
+    assert_eq!(args, ["_name", "build"]);
+}
+
 fn test_app<C, F>(setup: F, expected: C)
 where
-    C: CommandFactory + FromArgMatches + Debug + Eq,
+    C: CommandFactory + FromArgMatches + Debug + PartialEq,
     F: FnOnce(&mut Vec<ArgState>),
 {
     let app = C::command();
@@ -250,6 +614,20 @@ impl crate::arg_state::ArgState<'_> {
         }
     }
 
+    fn number(&mut self, val: i64) {
+        match &mut self.kind {
+            ArgKind::Int { value, enabled, .. } => {
+                *value = val;
+                *enabled = true;
+            }
+            ArgKind::UInt { value, enabled, .. } => {
+                *value = val as u64;
+                *enabled = true;
+            }
+            _ => panic!("Called number on {:?}", self),
+        }
+    }
+
     fn set(&mut self, val: bool) {
         if let ArgKind::Bool(b) = &mut self.kind {
             *b = val;
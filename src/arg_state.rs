@@ -1,12 +1,20 @@
-use crate::{settings::Localization, to_sentence_case};
-use clap::{Arg, ValueHint};
-use eframe::egui::{widgets::Widget, ComboBox, Response, TextEdit, Ui};
+use crate::{preset::PresetValue, settings::Localization, to_sentence_case};
+use clap::{builder::ValueParser, parser::ValueSource, Arg, ArgMatches, Command, ValueHint};
+use eframe::egui::{
+    emath::Numeric, widgets::Widget, ComboBox, DragValue, Label, Response, Slider, TextEdit, Ui,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use rfd::FileDialog;
+use std::{any::TypeId, ops::RangeInclusive};
 use uuid::Uuid;
 
+/// Ranges with at most this many values are displayed as a slider.
+const MAX_SLIDER_SPAN: i128 = 1000;
+
 #[derive(Debug, Clone)]
 pub struct ArgState<'s> {
+    /// Id of the arg in clap.
+    pub id: String,
     pub name: String,
     pub call_name: Option<String>,
     pub desc: Option<String>,
@@ -14,7 +22,20 @@ pub struct ArgState<'s> {
     pub use_equals: bool,
     pub forbid_empty: bool,
     pub kind: ArgKind,
+    /// Error from the last run.
     pub validation_error: Option<String>,
+    /// Error from running the value parser on the current values.
+    pub value_error: Option<String>,
+    pub value_parser: ValueParser,
+    /// Values the [`ArgState::value_error`] was computed for.
+    checked_values: Vec<String>,
+    /// Ids of args that can't be used together with this one.
+    pub conflicts: Vec<String>,
+    /// Required because of `requires`, `required_unless_present`, `required_if_eq` etc.
+    /// Recomputed by [`crate::app_state::AppState`] whenever the form changes.
+    pub relation_required: bool,
+    /// Set when a conflicting arg is filled.
+    pub disabled: bool,
     pub localization: &'s Localization,
 }
 
@@ -34,10 +55,37 @@ pub enum ArgKind {
     },
     Occurrences(u8),
     Bool(bool),
+    /// Value parsed by a signed integer value parser.
+    Int {
+        value: i64,
+        /// Optional args without a value aren't passed
+        enabled: bool,
+        range: RangeInclusive<i64>,
+        slider: bool,
+    },
+    /// Value parsed by an unsigned integer value parser.
+    UInt {
+        value: u64,
+        /// Optional args without a value aren't passed
+        enabled: bool,
+        range: RangeInclusive<u64>,
+        slider: bool,
+    },
+    /// Value parsed by a float value parser.
+    Float {
+        value: f64,
+        /// Optional args without a value aren't passed
+        enabled: bool,
+    },
+    /// Bool that takes a value, e.g. `--flag true`.
+    BoolValue {
+        value: bool,
+        default: bool,
+    },
 }
 
 impl<'s> ArgState<'s> {
-    pub fn new(arg: &Arg, localization: &'s Localization) -> Self {
+    pub fn new(arg: &Arg, app: &Command, localization: &'s Localization) -> Self {
         let default: Vec<String> = arg
             .get_default_values()
             .iter()
@@ -51,12 +99,13 @@ impl<'s> ArgState<'s> {
         };
 
         let kind = match *arg.get_action() {
-            clap::ArgAction::Set => ArgKind::String {
-                value: (String::new(), Uuid::new_v4()),
-                default: default.get(0).map(|v| v.to_string()),
-                possible,
-                value_hint: arg.get_value_hint(),
-            },
+            clap::ArgAction::Set => ArgKind::typed(arg, default.first(), !arg.is_required_set())
+                .unwrap_or_else(|| ArgKind::String {
+                    value: (String::new(), Uuid::new_v4()),
+                    default: default.first().map(|v| v.to_string()),
+                    possible,
+                    value_hint: arg.get_value_hint(),
+                }),
             clap::ArgAction::Append => ArgKind::MultipleStrings {
                 values: vec![],
                 default,
@@ -69,7 +118,20 @@ impl<'s> ArgState<'s> {
             _ => ArgKind::Bool(false),
         };
 
+        let conflicts = if arg.is_exclusive_set() {
+            app.get_arguments()
+                .filter(|a| a.get_id() != arg.get_id())
+                .map(|a| a.get_id().to_string())
+                .collect()
+        } else {
+            app.get_arg_conflicts_with(arg)
+                .into_iter()
+                .map(|a| a.get_id().to_string())
+                .collect()
+        };
+
         Self {
+            id: arg.get_id().to_string(),
             name: to_sentence_case(arg.get_id().as_ref()),
             call_name: arg
                 .get_long()
@@ -85,12 +147,201 @@ impl<'s> ArgState<'s> {
             forbid_empty: false,
             kind,
             validation_error: None,
+            value_error: None,
+            value_parser: arg.get_value_parser().clone(),
+            checked_values: vec![],
+            conflicts,
+            relation_required: false,
+            disabled: false,
             localization,
         }
     }
 
-    pub fn update_validation_error(&mut self, name: &str, message: &str) {
-        self.validation_error = (self.name == name).then(|| message.to_string());
+    pub fn is_optional(&self) -> bool {
+        self.optional && !self.relation_required
+    }
+
+    /// Whether the arg will appear in the command line.
+    pub fn is_set(&self) -> bool {
+        match &self.kind {
+            ArgKind::String { value, .. } => !value.0.is_empty(),
+            ArgKind::MultipleStrings { values, .. } => !values.is_empty(),
+            &ArgKind::Occurrences(i) => i > 0,
+            &ArgKind::Bool(bool) => bool,
+            &ArgKind::Int { enabled, .. }
+            | &ArgKind::UInt { enabled, .. }
+            | &ArgKind::Float { enabled, .. } => enabled,
+            &ArgKind::BoolValue { value, default } => value != default || !self.is_optional(),
+        }
+    }
+
+    /// Clear the value so the arg won't appear in the command line.
+    pub fn reset(&mut self) {
+        match &mut self.kind {
+            ArgKind::String { value, .. } => value.0.clear(),
+            ArgKind::MultipleStrings { values, .. } => values.clear(),
+            ArgKind::Occurrences(i) => *i = 0,
+            ArgKind::Bool(bool) => *bool = false,
+            ArgKind::Int { enabled, .. }
+            | ArgKind::UInt { enabled, .. }
+            | ArgKind::Float { enabled, .. } => *enabled = false,
+            ArgKind::BoolValue { value, default } => *value = *default,
+        }
+    }
+
+    /// Value to store in a [`crate::Preset`], `None` if the arg isn't set.
+    pub fn preset_value(&self) -> Option<PresetValue> {
+        if !self.is_set() {
+            return None;
+        }
+        Some(match &self.kind {
+            ArgKind::String { value, .. } => PresetValue::String(value.0.clone()),
+            ArgKind::MultipleStrings { values, .. } => {
+                PresetValue::Strings(values.iter().map(|(v, _)| v.clone()).collect())
+            }
+            &ArgKind::Occurrences(i) => PresetValue::Int(i.into()),
+            &ArgKind::Bool(bool) => PresetValue::Bool(bool),
+            &ArgKind::Int { value, .. } => PresetValue::Int(value),
+            &ArgKind::UInt { value, .. } => PresetValue::UInt(value),
+            &ArgKind::Float { value, .. } => PresetValue::Float(value),
+            &ArgKind::BoolValue { value, .. } => PresetValue::Bool(value),
+        })
+    }
+
+    /// Set the value from a [`crate::Preset`]. Values of the wrong type are ignored.
+    pub fn set_preset_value(&mut self, preset: Option<&PresetValue>) {
+        self.reset();
+        let Some(preset) = preset else {
+            return;
+        };
+
+        match (&mut self.kind, preset) {
+            (ArgKind::String { value, .. }, PresetValue::String(v)) => value.0 = v.clone(),
+            (ArgKind::MultipleStrings { values, .. }, PresetValue::Strings(v)) => {
+                *values = v.iter().map(|v| (v.clone(), Uuid::new_v4())).collect()
+            }
+            (ArgKind::Occurrences(i), v) => {
+                *i = v.as_u64().and_then(|v| v.try_into().ok()).unwrap_or(*i)
+            }
+            (ArgKind::Bool(bool), &PresetValue::Bool(v))
+            | (ArgKind::BoolValue { value: bool, .. }, &PresetValue::Bool(v)) => *bool = v,
+            (ArgKind::Int { value, enabled, .. }, v) => {
+                if let Some(v) = v.as_i64() {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            (ArgKind::UInt { value, enabled, .. }, v) => {
+                if let Some(v) = v.as_u64() {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            (ArgKind::Float { value, enabled }, v) => {
+                if let Some(v) = v.as_f64() {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Set the value to what was passed on the command line in `matches`.
+    pub fn set_from_matches(&mut self, matches: &ArgMatches) {
+        self.reset();
+        // Accessing an id that isn't in matches panics in debug builds
+        if !matches.ids().any(|id| id == self.id.as_str())
+            || matches.value_source(&self.id) != Some(ValueSource::CommandLine)
+        {
+            return;
+        }
+
+        let raw: Vec<String> = match matches.try_get_raw(&self.id) {
+            Ok(Some(raw)) => raw.map(|v| v.to_string_lossy().into_owned()).collect(),
+            _ => vec![],
+        };
+        let first = raw.first();
+
+        match &mut self.kind {
+            ArgKind::String { value, .. } => value.0 = first.cloned().unwrap_or_default(),
+            ArgKind::MultipleStrings { values, .. } => {
+                *values = raw.into_iter().map(|v| (v, Uuid::new_v4())).collect()
+            }
+            ArgKind::Occurrences(i) => {
+                *i = matches
+                    .try_get_one::<u8>(&self.id)
+                    .ok()
+                    .flatten()
+                    .copied()
+                    .unwrap_or_default()
+            }
+            ArgKind::Bool(bool) => *bool = true,
+            ArgKind::Int { value, enabled, .. } => {
+                if let Some(v) = first.and_then(|v| v.parse().ok()) {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            ArgKind::UInt { value, enabled, .. } => {
+                if let Some(v) = first.and_then(|v| v.parse().ok()) {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            ArgKind::Float { value, enabled } => {
+                if let Some(v) = first.and_then(|v| v.parse().ok()) {
+                    *value = v;
+                    *enabled = true;
+                }
+            }
+            ArgKind::BoolValue { value, .. } => {
+                if let Ok(Some(&v)) = matches.try_get_one::<bool>(&self.id) {
+                    *value = v;
+                }
+            }
+        }
+    }
+
+    /// Run the value parser on values that changed since the last check.
+    pub fn update_value_error(&mut self) {
+        let values = match &self.kind {
+            ArgKind::String { value, .. } => vec![value.0.clone()],
+            ArgKind::MultipleStrings { values, .. } => {
+                values.iter().map(|(v, _)| v.clone()).collect()
+            }
+            // Numbers are in range, but a custom value parser could still reject them
+            ArgKind::Int { value, enabled, .. } => {
+                enabled.then(|| value.to_string()).into_iter().collect()
+            }
+            ArgKind::UInt { value, enabled, .. } => {
+                enabled.then(|| value.to_string()).into_iter().collect()
+            }
+            ArgKind::Float { value, enabled } => {
+                enabled.then(|| value.to_string()).into_iter().collect()
+            }
+            ArgKind::BoolValue { value, .. } => vec![value.to_string()],
+            ArgKind::Occurrences(_) | ArgKind::Bool(_) => return,
+        };
+        if values == self.checked_values {
+            return;
+        }
+
+        // Error from the last run no longer applies
+        self.validation_error = None;
+        self.value_error = values
+            .iter()
+            .filter(|v| !v.is_empty())
+            .find_map(|v| parse_value(&self.value_parser, v).err())
+            .map(|err| match std::error::Error::source(&err) {
+                Some(source) => source.to_string(),
+                None => err.kind().to_string(),
+            });
+        self.checked_values = values;
+    }
+
+    pub fn update_validation_error(&mut self, ids: &[String], message: &str) {
+        self.validation_error = ids.contains(&self.id).then(|| message.to_string());
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -169,28 +420,33 @@ impl<'s> ArgState<'s> {
         inner_response.response
     }
 
-    pub fn get_cmd_args(&self, mut args: Vec<String>) -> Result<Vec<String>, String> {
+    pub fn get_cmd_args(&self, args: Vec<String>) -> Result<Vec<String>, String> {
+        let has_value = matches!(
+            self.kind,
+            ArgKind::String { .. }
+                | ArgKind::Int { .. }
+                | ArgKind::UInt { .. }
+                | ArgKind::Float { .. }
+        );
+        if !self.is_optional() && !self.is_set() && has_value {
+            return Err(format!(
+                "{}{}{}",
+                self.localization.error_is_required.0,
+                self.name,
+                self.localization.error_is_required.1
+            ));
+        }
+        self.get_partial_cmd_args(args)
+    }
+
+    /// Like [`ArgState::get_cmd_args`] but doesn't fail on missing required values.
+    pub fn get_partial_cmd_args(&self, mut args: Vec<String>) -> Result<Vec<String>, String> {
         match &self.kind {
             ArgKind::String {
                 value: (value, _), ..
             } => {
                 if !value.is_empty() {
-                    if let Some(call_name) = self.call_name.as_ref() {
-                        if self.use_equals {
-                            args.push(format!("{call_name}={value}"));
-                        } else {
-                            args.extend_from_slice(&[call_name.clone(), value.clone()]);
-                        }
-                    } else {
-                        args.push(value.clone());
-                    }
-                } else if !self.optional {
-                    return Err(format!(
-                        "{}{}{}",
-                        self.localization.error_is_required.0,
-                        self.name,
-                        self.localization.error_is_required.1
-                    ));
+                    self.push_value(&mut args, value.clone());
                 }
             }
             ArgKind::MultipleStrings { values, .. } => {
@@ -226,16 +482,209 @@ impl<'s> ArgState<'s> {
                     args.push(self.call_name.clone().unwrap_or_else(|| "true".to_owned()));
                 }
             }
+            &ArgKind::Int { value, enabled, .. } => match &self.call_name {
+                // Otherwise a negative value could be mistaken for a flag
+                Some(call_name) if enabled && value < 0 => {
+                    args.push(format!("{call_name}={value}"));
+                }
+                _ if enabled => self.push_value(&mut args, value.to_string()),
+                _ => {}
+            },
+            &ArgKind::UInt { value, enabled, .. } => {
+                if enabled {
+                    self.push_value(&mut args, value.to_string());
+                }
+            }
+            &ArgKind::Float { value, enabled } => match &self.call_name {
+                // Otherwise a negative value could be mistaken for a flag
+                Some(call_name) if enabled && value.is_sign_negative() => {
+                    args.push(format!("{call_name}={value}"));
+                }
+                _ if enabled => self.push_value(&mut args, value.to_string()),
+                _ => {}
+            },
+            &ArgKind::BoolValue { value, .. } => {
+                if self.is_set() {
+                    self.push_value(&mut args, value.to_string());
+                }
+            }
         }
 
         Ok(args)
     }
+
+    fn push_value(&self, args: &mut Vec<String>, value: String) {
+        if let Some(call_name) = self.call_name.as_ref() {
+            if self.use_equals {
+                args.push(format!("{call_name}={value}"));
+            } else {
+                args.extend_from_slice(&[call_name.clone(), value]);
+            }
+        } else {
+            args.push(value);
+        }
+    }
+}
+
+impl ArgKind {
+    /// Picks a widget based on the type produced by the arg's value parser.
+    /// Returns `None` if there is no specialized widget for the type.
+    fn typed(arg: &Arg, default: Option<&String>, optional: bool) -> Option<Self> {
+        let type_id = arg.get_value_parser().type_id();
+        // Required args start with their default, others without a value
+        let enabled = !optional && default.is_some();
+
+        let integers: [(TypeId, i128, i128); 10] = [
+            (TypeId::of::<i8>(), i8::MIN.into(), i8::MAX.into()),
+            (TypeId::of::<i16>(), i16::MIN.into(), i16::MAX.into()),
+            (TypeId::of::<i32>(), i32::MIN.into(), i32::MAX.into()),
+            (TypeId::of::<i64>(), i64::MIN.into(), i64::MAX.into()),
+            (
+                TypeId::of::<isize>(),
+                isize::MIN as i128,
+                isize::MAX as i128,
+            ),
+            (TypeId::of::<u8>(), u8::MIN.into(), u8::MAX.into()),
+            (TypeId::of::<u16>(), u16::MIN.into(), u16::MAX.into()),
+            (TypeId::of::<u32>(), u32::MIN.into(), u32::MAX.into()),
+            (TypeId::of::<u64>(), u64::MIN.into(), u64::MAX.into()),
+            (
+                TypeId::of::<usize>(),
+                usize::MIN as i128,
+                usize::MAX as i128,
+            ),
+        ];
+
+        Some(
+            if let Some(&(_, type_min, type_max)) = integers.iter().find(|(t, ..)| type_id == *t) {
+                let default = default.and_then(|d| d.parse().ok());
+                let (min, max) = parser_range(arg.get_value_parser(), default, type_min, type_max);
+                let value = default.unwrap_or(0).clamp(min, max);
+                let slider = max - min <= MAX_SLIDER_SPAN;
+                if type_min < 0 {
+                    ArgKind::Int {
+                        value: value as i64,
+                        enabled,
+                        range: min as i64..=max as i64,
+                        slider,
+                    }
+                } else {
+                    ArgKind::UInt {
+                        value: value as u64,
+                        enabled,
+                        range: min as u64..=max as u64,
+                        slider,
+                    }
+                }
+            } else if type_id == TypeId::of::<f64>() || type_id == TypeId::of::<f32>() {
+                ArgKind::Float {
+                    value: default.and_then(|d| d.parse().ok()).unwrap_or_default(),
+                    enabled,
+                }
+            } else if type_id == TypeId::of::<bool>() {
+                let default = default.and_then(|d| d.parse().ok()).unwrap_or_default();
+                ArgKind::BoolValue {
+                    value: default,
+                    default,
+                }
+            } else {
+                return None;
+            },
+        )
+    }
+}
+
+/// Bounds of a ranged integer value parser within `min..=max`, the bounds of its type.
+/// Clap doesn't expose them, so they are found by parsing values. Assumes the valid values
+/// are contiguous, like the ranges clap supports.
+fn parser_range(
+    value_parser: &ValueParser,
+    default: Option<i128>,
+    min: i128,
+    max: i128,
+) -> (i128, i128) {
+    let valid = |value: i128| parse_value(value_parser, &value.to_string()).is_ok();
+
+    // A valid value to search from, usual ranges contain a small number or a power of two
+    let powers = (0..127)
+        .map(|exp| 1i128 << exp)
+        .take_while(|&p| p <= max.max(-min));
+    let Some(start) = default
+        .into_iter()
+        .chain(0..=16)
+        .chain(powers.flat_map(|p| [p, -p]))
+        .chain([min, max])
+        .filter(|value| (min..=max).contains(value))
+        .find(|&value| valid(value))
+    else {
+        return (min, max);
+    };
+
+    // Binary search for the last invalid value on each side of `start`
+    let bound = |mut valid_end: i128, mut invalid_end: i128| {
+        if valid(invalid_end) {
+            return invalid_end;
+        }
+        while (valid_end - invalid_end).abs() > 1 {
+            let middle = invalid_end + (valid_end - invalid_end) / 2;
+            if valid(middle) {
+                valid_end = middle;
+            } else {
+                invalid_end = middle;
+            }
+        }
+        valid_end
+    };
+    (bound(start, min), bound(start, max))
+}
+
+/// Runs a value parser on a single value.
+/// Clap can only run a [`ValueParser`] as part of a [`Command`].
+pub fn parse_value(value_parser: &ValueParser, value: &str) -> Result<(), clap::Error> {
+    Command::new("klask")
+        .no_binary_name(true)
+        .arg(
+            Arg::new("value")
+                .value_parser(value_parser.clone())
+                .allow_hyphen_values(true),
+        )
+        .try_get_matches_from([value])
+        .map(drop)
+}
+
+fn ui_number<T: Numeric>(
+    ui: &mut Ui,
+    value: &mut T,
+    enabled: &mut bool,
+    range: RangeInclusive<T>,
+    slider: bool,
+    optional: bool,
+) -> Response {
+    ui.horizontal(|ui| {
+        // Required args keep the checkbox until they have a value
+        if optional || !*enabled {
+            ui.checkbox(enabled, "");
+        }
+        let editable = *enabled || !optional;
+        ui.add_enabled_ui(editable, |ui| {
+            let response = if slider {
+                ui.add(Slider::new(value, range))
+            } else {
+                ui.add(DragValue::new(value).clamp_range(range))
+            };
+            if response.changed() {
+                *enabled = true;
+            }
+            response
+        })
+        .inner
+    })
+    .inner
 }
 
 impl Widget for &mut ArgState<'_> {
     fn ui(self, ui: &mut Ui) -> eframe::egui::Response {
-        let localization = self.localization;
-        let label = ui.label(&self.name);
+        let label = ui.add_enabled(!self.disabled, Label::new(&self.name));
 
         if let Some(desc) = &self.desc {
             label.on_hover_text(desc);
@@ -243,8 +692,28 @@ impl Widget for &mut ArgState<'_> {
 
         // Grid column automatically switches here
 
-        let is_validation_error = self.validation_error.is_some();
+        self.update_value_error();
+        let error = self.value_error.clone().or(self.validation_error.clone());
+        let is_validation_error = error.is_some();
+        let optional = self.is_optional();
 
+        ui.add_enabled_ui(!self.disabled, |ui| {
+            ui.vertical(|ui| {
+                let response = self.ui_value(ui, is_validation_error, optional);
+                if let Some(error) = &error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                response
+            })
+            .inner
+        })
+        .inner
+    }
+}
+
+impl ArgState<'_> {
+    fn ui_value(&mut self, ui: &mut Ui, is_validation_error: bool, optional: bool) -> Response {
+        let localization = self.localization;
         match &mut self.kind {
             ArgKind::String {
                 value,
@@ -257,7 +726,7 @@ impl Widget for &mut ArgState<'_> {
                 default,
                 possible,
                 *value_hint,
-                self.optional && !self.forbid_empty,
+                optional && !self.forbid_empty,
                 is_validation_error,
                 localization,
             ),
@@ -269,68 +738,58 @@ impl Widget for &mut ArgState<'_> {
                 ..
             } => {
                 let forbid_empty = self.forbid_empty;
-                let mut list = ui
-                    .vertical(|ui| {
-                        let mut remove_index = None;
-
-                        for (index, value) in values.iter_mut().enumerate() {
-                            ui.horizontal(|ui| {
-                                if ui.small_button("-").clicked() {
-                                    remove_index = Some(index);
-                                }
-
-                                ArgState::ui_single_row(
-                                    ui,
-                                    value,
-                                    &None,
-                                    possible,
-                                    *value_hint,
-                                    !forbid_empty,
-                                    is_validation_error,
-                                    localization,
-                                );
-                            });
-                        }
-
-                        if let Some(index) = remove_index {
-                            values.remove(index);
-                        }
+                ui.vertical(|ui| {
+                    let mut remove_index = None;
 
+                    for (index, value) in values.iter_mut().enumerate() {
                         ui.horizontal(|ui| {
-                            if ui.button(&localization.new_value).clicked() {
-                                values.push((String::new(), Uuid::new_v4()));
+                            if ui.small_button("-").clicked() {
+                                remove_index = Some(index);
                             }
 
-                            let text = if default.is_empty() {
-                                &localization.reset
-                            } else {
-                                &localization.reset_to_default
-                            };
-
-                            ui.add_space(20.0);
-                            if ui.button(text).clicked() {
-                                *values = default
-                                    .iter()
-                                    .map(|s| (s.to_string(), Uuid::new_v4()))
-                                    .collect();
-                            }
+                            ArgState::ui_single_row(
+                                ui,
+                                value,
+                                &None,
+                                possible,
+                                *value_hint,
+                                !forbid_empty,
+                                is_validation_error,
+                                localization,
+                            );
                         });
-                    })
-                    .response;
+                    }
 
-                if let Some(message) = &self.validation_error {
-                    list = list.on_hover_text(message);
-                    if list.changed() {
-                        self.validation_error = None;
+                    if let Some(index) = remove_index {
+                        values.remove(index);
                     }
-                }
 
-                list
+                    ui.horizontal(|ui| {
+                        if ui.button(&localization.new_value).clicked() {
+                            values.push((String::new(), Uuid::new_v4()));
+                        }
+
+                        let text = if default.is_empty() {
+                            &localization.reset
+                        } else {
+                            &localization.reset_to_default
+                        };
+
+                        ui.add_space(20.0);
+                        if ui.button(text).clicked() {
+                            *values = default
+                                .iter()
+                                .map(|s| (s.to_string(), Uuid::new_v4()))
+                                .collect();
+                        }
+                    });
+                })
+                .response
             }
             ArgKind::Occurrences(i) => {
                 ui.horizontal(|ui| {
                     if ui.small_button("-").clicked() {
-                        *i = (*i - 1).max(0);
+                        *i = i.saturating_sub(1);
                     }
 
                     ui.label(i.to_string());
@@ -342,6 +801,27 @@ impl Widget for &mut ArgState<'_> {
                 .response
             }
             ArgKind::Bool(bool) => ui.checkbox(bool, ""),
+            ArgKind::Int {
+                value,
+                enabled,
+                range,
+                slider,
+            } => ui_number(ui, value, enabled, range.clone(), *slider, optional),
+            ArgKind::UInt {
+                value,
+                enabled,
+                range,
+                slider,
+            } => ui_number(ui, value, enabled, range.clone(), *slider, optional),
+            ArgKind::Float { value, enabled } => ui_number(
+                ui,
+                value,
+                enabled,
+                f64::NEG_INFINITY..=f64::INFINITY,
+                false,
+                optional,
+            ),
+            ArgKind::BoolValue { value, .. } => ui.checkbox(value, ""),
         }
     }
 }
@@ -0,0 +1,372 @@
+//! Storage for the output of a run that stays fast with millions of lines.
+
+use crate::output::{OutputType, Stream};
+use crate::settings::Palette;
+use crate::terminal;
+use eframe::egui::{Color32, Galley};
+use regex::Regex;
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    sync::Arc,
+};
+
+/// A line laid out for painting.
+#[derive(Debug, Clone)]
+pub(crate) struct LineLayout {
+    pub galley: Arc<Galley>,
+    /// Character ranges of links and where they lead.
+    pub links: Vec<(Range<usize>, String)>,
+}
+
+/// Everything besides the text that a [`LineLayout`] depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LayoutKey {
+    pub palette: Palette,
+    pub stderr_color: Color32,
+    pub text_color: Color32,
+    pub pixels_per_point: f32,
+}
+
+/// Lines of output indexed from the start of the run. The oldest lines are dropped
+/// once there are more than `max_lines`.
+#[derive(Debug)]
+pub(crate) struct OutputBuffer {
+    lines: VecDeque<(u64, OutputType)>,
+    /// Number of lines dropped from the start, the index of the first line.
+    dropped: usize,
+    max_lines: usize,
+    /// Indexes of the lines of each stream. Progress bars are printed to stdout.
+    stdout: VecDeque<usize>,
+    stderr: VecDeque<usize>,
+    /// Index of each progress bar by its id.
+    progress_bars: HashMap<u64, usize>,
+    /// Layouts of recently painted lines with the frame they were last used in.
+    layouts: HashMap<usize, (u64, LineLayout)>,
+    layout_key: Option<LayoutKey>,
+    search: Option<SearchCache>,
+}
+
+/// Matches of a search, found incrementally as lines are added.
+#[derive(Debug)]
+struct SearchCache {
+    regex: Regex,
+    /// Matches in every line, and in the lines of each stream.
+    all: Matches,
+    stdout: Matches,
+    stderr: Matches,
+    /// Index of the first line not searched yet.
+    searched: usize,
+}
+
+impl SearchCache {
+    fn lists(&mut self) -> [&mut Matches; 3] {
+        [&mut self.all, &mut self.stdout, &mut self.stderr]
+    }
+}
+
+/// Matches of a search in order.
+#[derive(Debug, Default)]
+pub(crate) struct Matches {
+    /// Indexes of the lines with a match.
+    pub lines: VecDeque<usize>,
+    /// Index of the line and byte range in its text without ANSI codes of every match.
+    pub found: VecDeque<(usize, Range<usize>)>,
+}
+
+impl Matches {
+    fn push(&mut self, index: usize, found: &[Range<usize>]) {
+        self.lines.push_back(index);
+        self.found
+            .extend(found.iter().map(|range| (index, range.clone())));
+    }
+
+    /// Drops the matches in lines from `index` on.
+    fn truncate(&mut self, index: usize) {
+        while self.lines.back().is_some_and(|&i| i >= index) {
+            self.lines.pop_back();
+        }
+        while self.found.back().is_some_and(|(i, _)| *i >= index) {
+            self.found.pop_back();
+        }
+    }
+
+    /// Drops the matches in the line at `index`, the first one.
+    fn pop_line(&mut self, index: usize) {
+        if self.lines.front() == Some(&index) {
+            self.lines.pop_front();
+        }
+        while self.found.front().is_some_and(|(i, _)| *i == index) {
+            self.found.pop_front();
+        }
+    }
+}
+
+impl Default for OutputBuffer {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+impl OutputBuffer {
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            dropped: 0,
+            max_lines: max_lines.max(1),
+            stdout: VecDeque::new(),
+            stderr: VecDeque::new(),
+            progress_bars: HashMap::new(),
+            layouts: HashMap::new(),
+            layout_key: None,
+            search: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Number of lines dropped because of the limit.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Adds a line, returning its index.
+    pub fn push(&mut self, id: u64, line: OutputType) -> usize {
+        let index = self.dropped + self.lines.len();
+        match line {
+            OutputType::Text(_, Stream::Stderr, _) => self.stderr.push_back(index),
+            _ => self.stdout.push_back(index),
+        }
+        self.lines.push_back((id, line));
+
+        while self.lines.len() > self.max_lines {
+            let first = self.dropped;
+            if let Some((id, _)) = self.lines.pop_front() {
+                if self.progress_bars.get(&id) == Some(&first) {
+                    self.progress_bars.remove(&id);
+                }
+            }
+            for indexes in [&mut self.stdout, &mut self.stderr] {
+                if indexes.front() == Some(&first) {
+                    indexes.pop_front();
+                }
+            }
+            self.layouts.remove(&first);
+            if let Some(search) = &mut self.search {
+                for matches in search.lists() {
+                    matches.pop_line(first);
+                }
+            }
+            self.dropped += 1;
+        }
+        index
+    }
+
+    pub fn get(&self, index: usize) -> Option<&OutputType> {
+        let (_, line) = self.lines.get(index.checked_sub(self.dropped)?)?;
+        Some(line)
+    }
+
+    /// Line at `index` to be changed, its layout is made again.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut OutputType> {
+        self.layouts.remove(&index);
+        if let Some(search) = &mut self.search {
+            if index < search.searched {
+                // Search it again, it's usually one of the last lines
+                search.searched = index;
+                for matches in search.lists() {
+                    matches.truncate(index);
+                }
+            }
+        }
+        let (_, line) = self.lines.get_mut(index.checked_sub(self.dropped)?)?;
+        Some(line)
+    }
+
+    /// Adds a progress bar or replaces the one with the same id.
+    pub fn set_progress_bar(&mut self, id: u64, bar: OutputType) {
+        if let Some(&index) = self.progress_bars.get(&id) {
+            if let Some(line) = self.get_mut(index) {
+                *line = bar;
+                return;
+            }
+        }
+        let index = self.push(id, bar);
+        self.progress_bars.insert(id, index);
+    }
+
+    /// Number of lines shown when only showing `stream`, or every line for `None`.
+    pub fn shown_len(&self, stream: Option<Stream>) -> usize {
+        match stream {
+            None => self.lines.len(),
+            Some(Stream::Stdout) => self.stdout.len(),
+            Some(Stream::Stderr) => self.stderr.len(),
+        }
+    }
+
+    /// Index of the line in `row` when only showing `stream`.
+    pub fn shown_index(&self, stream: Option<Stream>, row: usize) -> usize {
+        match stream {
+            None => self.dropped + row,
+            Some(Stream::Stdout) => self.stdout[row],
+            Some(Stream::Stderr) => self.stderr[row],
+        }
+    }
+
+    /// Lines shown when only showing `stream`.
+    pub fn shown(&self, stream: Option<Stream>) -> impl Iterator<Item = &OutputType> {
+        (0..self.shown_len(stream)).filter_map(move |row| self.get(self.shown_index(stream, row)))
+    }
+
+    /// Row of the line at `index` when only showing `stream`.
+    pub fn shown_row(&self, stream: Option<Stream>, index: usize) -> Option<usize> {
+        match stream {
+            None => index.checked_sub(self.dropped),
+            Some(Stream::Stdout) => self.stdout.binary_search(&index).ok(),
+            Some(Stream::Stderr) => self.stderr.binary_search(&index).ok(),
+        }
+    }
+
+    /// Finds the lines where `regex` matches the text without ANSI codes. Only lines added or
+    /// changed since the last search with the same `regex` are searched.
+    pub fn search(&mut self, regex: &Regex) {
+        if let Some(search) = &self.search {
+            if search.regex.as_str() != regex.as_str() {
+                self.search = None;
+            }
+        }
+        let search = self.search.get_or_insert_with(|| SearchCache {
+            regex: regex.clone(),
+            all: Matches::default(),
+            stdout: Matches::default(),
+            stderr: Matches::default(),
+            searched: 0,
+        });
+
+        let start = search.searched.max(self.dropped);
+        for (offset, (_, line)) in self.lines.range(start - self.dropped..).enumerate() {
+            // Empty matches can't be highlighted
+            let text = plain_text(line);
+            let found: Vec<_> = regex
+                .find_iter(&text)
+                .filter(|found| !found.is_empty())
+                .map(|found| found.range())
+                .collect();
+            if found.is_empty() {
+                continue;
+            }
+            let index = start + offset;
+            search.all.push(index, &found);
+            match line {
+                OutputType::Text(_, Stream::Stderr, _) => search.stderr.push(index, &found),
+                _ => search.stdout.push(index, &found),
+            }
+        }
+        search.searched = self.dropped + self.lines.len();
+    }
+
+    /// Matches in the lines shown for `stream` found by the last [`Self::search`].
+    pub fn matches(&self, stream: Option<Stream>) -> &Matches {
+        static NONE: Matches = Matches {
+            lines: VecDeque::new(),
+            found: VecDeque::new(),
+        };
+        match (&self.search, stream) {
+            (None, _) => &NONE,
+            (Some(search), None) => &search.all,
+            (Some(search), Some(Stream::Stdout)) => &search.stdout,
+            (Some(search), Some(Stream::Stderr)) => &search.stderr,
+        }
+    }
+
+    /// Drops layouts that weren't used in the last frame, or all of them when `key` changed.
+    pub fn start_frame(&mut self, key: LayoutKey, frame: u64) {
+        if self.layout_key.as_ref() != Some(&key) {
+            self.layouts.clear();
+            self.layout_key = Some(key);
+        } else {
+            self.layouts.retain(|_, (used, _)| *used + 1 >= frame);
+        }
+    }
+
+    /// Layout of the line at `index`, made with `layout` unless it's cached. `layout` also
+    /// returns whether the result can be cached.
+    pub fn layout(
+        &mut self,
+        index: usize,
+        frame: u64,
+        layout: impl FnOnce(&OutputType) -> (LineLayout, bool),
+    ) -> Option<LineLayout> {
+        if let Some((used, cached)) = self.layouts.get_mut(&index) {
+            *used = frame;
+            return Some(cached.clone());
+        }
+        let (line, cache) = layout(self.get(index)?);
+        if cache {
+            self.layouts.insert(index, (frame, line.clone()));
+        }
+        Some(line)
+    }
+}
+
+/// Text of a line without ANSI codes and the ending newline, as it's shown.
+fn plain_text(line: &OutputType) -> String {
+    let text = match line {
+        OutputType::Text(text, ..) => text,
+        OutputType::ProgressBar(text, _) => text,
+    };
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    terminal::spans(text)
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect()
+}
+
+#[cfg(test)]
+fn text(text: &str, stream: Stream) -> OutputType {
+    OutputType::Text(text.into(), stream, std::time::Duration::ZERO)
+}
+
+#[test]
+fn test_output_buffer() {
+    let mut buffer = OutputBuffer::new(3);
+    buffer.push(0, text("a", Stream::Stdout));
+    buffer.push(0, text("b", Stream::Stderr));
+    buffer.set_progress_bar(7, OutputType::ProgressBar("bar".into(), 0.5));
+    buffer.set_progress_bar(7, OutputType::ProgressBar("bar".into(), 1.0));
+    assert_eq!(buffer.push(0, text("c", Stream::Stdout)), 3);
+
+    assert_eq!(buffer.dropped(), 1);
+    assert!(buffer.get(0).is_none());
+    assert_eq!(buffer.shown_len(None), 3);
+    assert_eq!(buffer.shown_len(Some(Stream::Stdout)), 2);
+    assert_eq!(buffer.shown_index(Some(Stream::Stderr), 0), 1);
+    assert!(matches!(buffer.get(2), Some(OutputType::ProgressBar(_, value)) if *value == 1.0));
+}
+
+#[test]
+fn test_matches() {
+    let regex = Regex::new("a+").unwrap();
+    let mut buffer = OutputBuffer::new(3);
+    buffer.push(0, text("\x1b[31maa\x1b[0m b a\n", Stream::Stdout));
+    buffer.push(0, text("b\n", Stream::Stderr));
+    assert!(buffer.matches(None).found.is_empty());
+    buffer.search(&regex);
+    assert_eq!(buffer.matches(None).lines, [0]);
+    assert_eq!(buffer.matches(None).found, [(0, 0..2), (0, 5..6)]);
+    assert!(buffer.matches(Some(Stream::Stderr)).found.is_empty());
+
+    buffer.set_progress_bar(7, OutputType::ProgressBar("bar\n".into(), 0.5));
+    buffer.search(&regex);
+    assert_eq!(buffer.matches(None).lines, [0, 2]);
+    buffer.set_progress_bar(7, OutputType::ProgressBar("progress\n".into(), 1.0));
+    buffer.push(0, text("a\n", Stream::Stdout));
+    buffer.push(0, text("aaa\n", Stream::Stderr));
+    buffer.search(&regex);
+    assert_eq!(buffer.matches(Some(Stream::Stdout)).found, [(3, 0..1)]);
+    assert_eq!(buffer.matches(Some(Stream::Stderr)).found, [(4, 0..3)]);
+    assert_eq!(buffer.matches(None).lines, [3, 4]);
+    assert_eq!(buffer.shown_row(Some(Stream::Stdout), 3), Some(1));
+}
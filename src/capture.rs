@@ -0,0 +1,96 @@
+//! Capturing the stdio of this process, for running the app on a thread.
+//!
+//! The redirected fds are inherited by processes the app starts, as they would be from a
+//! terminal. The readers only see the end of the output once those processes exit too.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Set while a [`Capture`] exists, the fds of the process can only be redirected once.
+static CAPTURED: AtomicBool = AtomicBool::new(false);
+
+/// Stdin, stdout and stderr of the whole process are pipes until this is dropped.
+/// Only one can exist at a time.
+#[derive(Debug)]
+pub struct Capture {
+    /// The replaced stdin, stdout and stderr.
+    saved: [OwnedFd; 3],
+}
+
+impl Capture {
+    /// Returns the capture with the other ends of the pipes: for writing stdin,
+    /// and for reading stdout and stderr.
+    pub fn start() -> io::Result<(Self, File, File, File)> {
+        if CAPTURED.swap(true, Ordering::AcqRel) {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "stdio is already captured for another run",
+            ));
+        }
+        let pipes = Self::redirect();
+        if pipes.is_err() {
+            CAPTURED.store(false, Ordering::Release);
+        }
+        pipes
+    }
+
+    fn redirect() -> io::Result<(Self, File, File, File)> {
+        let (stdin, stdin_writer) = pipe()?;
+        let (stdout_reader, stdout) = pipe()?;
+        let (stderr_reader, stderr) = pipe()?;
+        let saved = [
+            io::stdin().as_fd().try_clone_to_owned()?,
+            io::stdout().as_fd().try_clone_to_owned()?,
+            io::stderr().as_fd().try_clone_to_owned()?,
+        ];
+
+        // Output printed before belongs to the GUI
+        drop(io::stdout().flush());
+        for (fd, pipe) in [stdin, stdout, stderr].iter().enumerate() {
+            // SAFETY: both are valid fds
+            if unsafe { libc::dup2(pipe.as_raw_fd(), fd as libc::c_int) } == -1 {
+                let err = io::Error::last_os_error();
+                drop(Self { saved });
+                return Err(err);
+            }
+        }
+
+        Ok((
+            Self { saved },
+            File::from(stdin_writer),
+            File::from(stdout_reader),
+            File::from(stderr_reader),
+        ))
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        // Buffered output belongs to the app
+        drop(io::stdout().flush());
+        for (fd, saved) in self.saved.iter().enumerate() {
+            // SAFETY: both are valid fds. Replacing the pipes closes them, so readers get EOF.
+            unsafe { libc::dup2(saved.as_raw_fd(), fd as libc::c_int) };
+        }
+        CAPTURED.store(false, Ordering::Release);
+    }
+}
+
+/// Returns the read and write ends of a new pipe, not inherited by children.
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    // SAFETY: fds has room for the two fds
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    for fd in fds {
+        // SAFETY: fd was just opened
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    // SAFETY: pipe succeeded, so both are open fds owned by us
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
@@ -1,26 +1,176 @@
 #[cfg(target_arch = "wasm32")]
 use crate::logger::Logger;
+#[cfg(target_os = "linux")]
+use crate::resources::ResourceMonitor;
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+use crate::{capture::Capture, pty};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::{ExecutionError, CHILD_APP_ENV_VAR};
+use crate::{output::MAGIC, ExecutionError, CHILD_APP_ENV_VAR};
+use crate::{
+    output::{ExitStatus, RunStatus, Stream},
+    preset::PresetStdin,
+};
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+use clap::ArgMatches;
 use eframe::egui;
+use std::time::Duration;
 #[cfg(target_arch = "wasm32")]
 use std::{fmt::Debug, future::Future, pin::Pin, sync::Arc, task::Poll};
 #[cfg(not(target_arch = "wasm32"))]
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Read, Write},
+    io::{self, Read, Write},
     path::PathBuf,
     process::{Child, Command, Stdio},
-    sync::mpsc::{self, Receiver},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, OnceLock,
+    },
     thread,
+    time::Instant,
+};
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+use std::{
+    os::fd::OwnedFd,
+    panic::{self, AssertUnwindSafe},
+    sync::{Mutex, PoisonError},
 };
 
+/// Shared between the GUI and the thread writing to the child's stdin.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+struct StdinProgress {
+    sent: AtomicU64,
+    total: u64,
+    /// Set when the child is terminated or killed, stops writing the input.
+    cancelled: AtomicBool,
+    /// Reading the input failed, the rest of it isn't written.
+    error: OnceLock<io::Error>,
+}
+
+/// The user's closure when it runs on a thread instead of a new process.
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+pub type InProcessApp = Arc<Mutex<dyn FnMut(&ArgMatches) + Send>>;
+
+/// What runs the app.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+enum Process {
+    /// The binary started again.
+    Child {
+        child: Child,
+        /// Exit status once it was reaped, with its peak memory and CPU time where known.
+        exit: Option<(ExitStatus, Option<(u64, Duration)>)>,
+    },
+    /// A thread of the GUI process, with its exit status once it was joined.
+    #[cfg(unix)]
+    Thread {
+        handle: Option<thread::JoinHandle<ExitStatus>>,
+        exit: Option<ExitStatus>,
+    },
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<Child> for Process {
+    fn from(child: Child) -> Self {
+        Self::Child { child, exit: None }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Process {
+    /// Exit status once it has exited.
+    fn try_wait(&mut self) -> Option<ExitStatus> {
+        match self {
+            Self::Child { child, exit } => {
+                if exit.is_none() {
+                    *exit = reap(child);
+                }
+                exit.map(|(status, _)| status)
+            }
+            #[cfg(unix)]
+            Self::Thread { handle, exit } => {
+                if handle.as_ref().is_some_and(|h| h.is_finished()) {
+                    // Panics of the app are caught on the thread
+                    *exit = handle.take().and_then(|h| h.join().ok());
+                }
+                *exit
+            }
+        }
+    }
+
+    /// Peak memory and CPU time of the reaped child and the descendants it waited for.
+    fn usage(&self) -> Option<(u64, Duration)> {
+        match self {
+            Self::Child { exit, .. } => exit.and_then(|(_, usage)| usage),
+            #[cfg(unix)]
+            Self::Thread { .. } => None,
+        }
+    }
+
+    /// Process id, a thread has none and can't be sent signals.
+    fn id(&self) -> Option<u32> {
+        match self {
+            Self::Child { child, .. } => Some(child.id()),
+            #[cfg(unix)]
+            Self::Thread { .. } => None,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub struct ChildApp {
-    child: Child,
-    stdout: Option<Receiver<Option<String>>>,
-    stderr: Option<Receiver<Option<String>>>,
+    process: Process,
+    /// Lines from both stdout and stderr in the order they arrived. `None` marks the end of a stream.
+    output: Receiver<(Stream, Duration, Option<String>)>,
+    /// Number of streams that haven't ended yet.
+    open_streams: usize,
+    /// Master end of the pseudo-terminal the child runs in.
+    #[cfg(unix)]
+    pty: Option<OwnedFd>,
+    /// Interactive input for the stdin writer thread. Dropping it closes stdin.
+    stdin: Option<Sender<String>>,
+    stdin_progress: Arc<StdinProgress>,
+    ctx: egui::Context,
+    /// GUI time when the child was started.
+    started: f64,
+    /// Set when the child was terminated or killed.
+    killed: bool,
+    /// When to kill the process group after it was asked to terminate.
+    kill_at: Option<Instant>,
+    /// GUI time when the child was stopped with SIGSTOP, while it's paused.
+    paused: Option<f64>,
+    /// Time the child was paused before, not counted for the timeout.
+    paused_for: Duration,
+    /// Time after which the child is terminated, with the grace period for terminating it.
+    timeout: Option<(Duration, Duration)>,
+    timed_out: bool,
+    #[cfg(target_os = "linux")]
+    resources: Option<ResourceMonitor>,
+    status: Option<RunStatus>,
+}
+
+/// Signals that can be sent to the child from the GUI, with their names.
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+pub const SIGNALS: [(&str, libc::c_int); 3] = [
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGHUP", libc::SIGHUP),
+];
+
+/// How to stop a running child. On Unix signals are sent to its whole process group,
+/// elsewhere the child is always killed.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Like Ctrl+C in a terminal, sends SIGINT.
+    Interrupt,
+    /// Sends SIGTERM, then SIGKILL if it's still running after the grace period.
+    Terminate(Duration),
+    /// Sends SIGKILL.
+    Kill,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -30,6 +180,14 @@ pub struct ChildApp {
     fut: Option<Pin<Box<dyn Future<Output = ()>>>>,
     /// Logger contains a queue of logs to add to the display.
     logger: Arc<Logger>,
+    /// GUI time when the future was created.
+    started: f64,
+    /// Set when the future ran to completion.
+    completed: bool,
+    /// Time after which the future is dropped.
+    timeout: Option<Duration>,
+    timed_out: bool,
+    status: Option<RunStatus>,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -45,10 +203,14 @@ impl Debug for ChildApp {
                 },
             )
             .field("logger", &self.logger)
+            .field("status", &self.status)
             .finish()
     }
 }
 
+/// Columns and rows of the terminal the child runs in.
+pub type TerminalSize = (u16, u16);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum StdinType {
     #[cfg(not(target_arch = "wasm32"))]
@@ -56,6 +218,28 @@ pub enum StdinType {
     Text(String),
 }
 
+impl From<&StdinType> for PresetStdin {
+    fn from(stdin: &StdinType) -> Self {
+        match stdin {
+            #[cfg(not(target_arch = "wasm32"))]
+            StdinType::File(path) => PresetStdin::File(path.clone()),
+            StdinType::Text(text) => PresetStdin::Text(text.clone()),
+        }
+    }
+}
+
+impl From<&PresetStdin> for StdinType {
+    fn from(stdin: &PresetStdin) -> Self {
+        match stdin {
+            #[cfg(not(target_arch = "wasm32"))]
+            PresetStdin::File(path) => StdinType::File(path.clone()),
+            #[cfg(target_arch = "wasm32")]
+            PresetStdin::File(_) => StdinType::Text(String::new()),
+            PresetStdin::Text(text) => StdinType::Text(text.clone()),
+        }
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 impl ChildApp {
     pub fn poll(&mut self) -> Poll<()> {
@@ -65,6 +249,7 @@ impl ChildApp {
             ));
             // Request repaint after polling to update message output and to continue driving fut.
             self.ctx.request_repaint();
+            self.completed = poll_result.is_ready();
             poll_result
         } else {
             // If child has no future then it has already been exhausted.
@@ -72,14 +257,15 @@ impl ChildApp {
         }
     }
 
-    pub fn read(&mut self) -> String {
+    pub fn read(&mut self) -> Vec<(Stream, Duration, String)> {
+        let time = elapsed_since(&self.ctx, self.started);
         self.logger
             .queue
             .lock()
             .drain(..)
             .map(|mut x| {
                 x.push('\n'); // Concatenate messages with newlines
-                x
+                (Stream::Stdout, time, x)
             })
             .collect()
     }
@@ -89,18 +275,63 @@ impl ChildApp {
         self.fut.is_some()
     }
 
+    pub fn status(&self) -> Option<&RunStatus> {
+        self.status.as_ref()
+    }
+
+    /// Returns the status once, when the future is found finished or killed.
+    pub fn update_status(&mut self) -> Option<RunStatus> {
+        if self.remaining() == Some(Duration::ZERO) {
+            self.timed_out = true;
+            self.fut = None;
+        }
+        if self.status.is_some() || self.fut.is_some() {
+            return None;
+        }
+        let exit = if self.completed {
+            ExitStatus::Code(0)
+        } else if self.timed_out {
+            ExitStatus::TimedOut
+        } else {
+            ExitStatus::Killed
+        };
+        self.status = Some(RunStatus {
+            exit,
+            elapsed: elapsed_since(&self.ctx, self.started),
+            peak_memory: None,
+            cpu_time: None,
+        });
+        self.status.clone()
+    }
+
     pub fn kill(&mut self) {
         self.fut = None;
     }
 
+    /// Drop the future once it ran for `timeout`. Futures can't be terminated gracefully.
+    pub fn set_timeout(&mut self, timeout: Duration, _grace_period: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Time left until the timeout while running.
+    pub fn remaining(&self) -> Option<Duration> {
+        let timeout = self.timeout.filter(|_| self.is_running())?;
+        Some(timeout.saturating_sub(elapsed_since(&self.ctx, self.started)))
+    }
+
     pub fn new<Fut>(ctx: egui::Context, fut: Fut, logger: Arc<Logger>) -> Self
     where
         Fut: Future<Output = ()> + 'static,
     {
         ChildApp {
+            started: ctx.input(|i| i.time),
             ctx,
             fut: Some(Box::pin(fut)),
             logger,
+            completed: false,
+            timeout: None,
+            timed_out: false,
+            status: None,
         }
     }
 }
@@ -112,6 +343,8 @@ impl ChildApp {
         env: Option<Vec<(String, String)>>,
         stdin: Option<StdinType>,
         working_dir: Option<String>,
+        interactive: bool,
+        terminal: Option<TerminalSize>,
         ctx: egui::Context,
     ) -> Result<Self, ExecutionError> {
         let mut child = Command::new(std::env::current_exe()?);
@@ -133,101 +366,1019 @@ impl ChildApp {
             }
         }
 
-        let mut child = child.spawn()?;
+        #[cfg(unix)]
+        let pty = match terminal {
+            Some(size) => {
+                let (master, slave) = pty::open(size)?;
+                pty::attach(&mut child, &slave, size)?;
+                Some(master)
+            }
+            None => None,
+        };
+        #[cfg(not(unix))]
+        let _ = terminal;
+        #[cfg(unix)]
+        if pty.is_none() {
+            // Signals reach the processes it starts too. In a pty it leads its own session.
+            std::os::unix::process::CommandExt::process_group(&mut child, 0);
+        }
+
+        let spawned = child.spawn();
+        // The command holds copies of the pty slave, the master only reads EOF once they're closed
+        drop(child);
+        let mut child = spawned?;
+        let start = Instant::now();
 
-        let stdout = Self::spawn_thread_reader(
+        let (tx, output) = mpsc::channel();
+        #[cfg(unix)]
+        if let Some(master) = &pty {
+            // Stdout and stderr are the same terminal
+            let writer = File::from(master.try_clone()?);
+            let reader = File::from(master.try_clone()?);
+            Self::spawn_thread_reader(reader, Stream::Stdout, start, tx, ctx.clone());
+            return Self::start(
+                Process::from(child),
+                output,
+                1,
+                writer,
+                pty,
+                stdin,
+                interactive,
+                ctx,
+            );
+        }
+
+        Self::spawn_thread_reader(
             child
                 .stdout
                 .take()
                 .ok_or(ExecutionError::NoStdoutOrStderr)?,
+            Stream::Stdout,
+            start,
+            tx.clone(),
             ctx.clone(),
         );
-
-        let stderr = Self::spawn_thread_reader(
+        Self::spawn_thread_reader(
             child
                 .stderr
                 .take()
                 .ok_or(ExecutionError::NoStdoutOrStderr)?,
-            ctx,
+            Stream::Stderr,
+            start,
+            tx,
+            ctx.clone(),
         );
 
-        if let Some(stdin) = stdin {
-            let mut child_stdin = child.stdin.take().unwrap();
-            match stdin {
-                StdinType::Text(text) => {
-                    child_stdin.write_all(text.as_bytes())?;
-                }
-                StdinType::File(path) => {
-                    let mut file = File::open(path)?;
-                    std::io::copy(&mut file, &mut child_stdin)?;
-                }
+        let writer = child.stdin.take().unwrap();
+        Self::start(
+            Process::from(child),
+            output,
+            2,
+            writer,
+            #[cfg(unix)]
+            None,
+            stdin,
+            interactive,
+            ctx,
+        )
+    }
+
+    /// Runs `app` on a new thread of this process. The stdio of the whole process
+    /// is captured until it returns, so prints of the GUI show up in the output too.
+    #[cfg(unix)]
+    pub fn run_in_process(
+        app: InProcessApp,
+        matches: ArgMatches,
+        stdin: Option<StdinType>,
+        interactive: bool,
+        ctx: egui::Context,
+    ) -> Result<Self, ExecutionError> {
+        let (capture, writer, stdout, stderr) = Capture::start()?;
+        let start = Instant::now();
+
+        let (tx, output) = mpsc::channel();
+        Self::spawn_thread_reader(stdout, Stream::Stdout, start, tx.clone(), ctx.clone());
+        Self::spawn_thread_reader(stderr, Stream::Stderr, start, tx, ctx.clone());
+
+        let handle = thread::spawn(move || {
+            // A panic in an earlier run poisons the lock, the closure can still be called
+            let mut app = app.lock().unwrap_or_else(PoisonError::into_inner);
+            let result = panic::catch_unwind(AssertUnwindSafe(|| app(&matches)));
+            // Closes the pipes, so the readers get EOF
+            drop(capture);
+            // Like a binary that panicked
+            ExitStatus::Code(if result.is_ok() { 0 } else { 101 })
+        });
+        let process = Process::Thread {
+            handle: Some(handle),
+            exit: None,
+        };
+        Self::start(process, output, 2, writer, None, stdin, interactive, ctx)
+    }
+
+    /// Starts writing `stdin` to `writer`.
+    #[allow(clippy::too_many_arguments)]
+    fn start(
+        process: Process,
+        output: Receiver<(Stream, Duration, Option<String>)>,
+        open_streams: usize,
+        writer: impl Write + Send + 'static,
+        #[cfg(unix)] pty: Option<OwnedFd>,
+        stdin: Option<StdinType>,
+        interactive: bool,
+        ctx: egui::Context,
+    ) -> Result<Self, ExecutionError> {
+        #[cfg(unix)]
+        let is_pty = pty.is_some();
+        #[cfg(not(unix))]
+        let is_pty = false;
+
+        // Open the file here so a missing file is reported as an error
+        let (input, total): (Box<dyn Read + Send>, u64) = match stdin {
+            Some(StdinType::Text(text)) => {
+                let len = text.len() as u64;
+                (Box::new(std::io::Cursor::new(text)), len)
             }
-        }
+            Some(StdinType::File(path)) => {
+                let file = File::open(path)?;
+                let len = file.metadata()?.len();
+                (Box::new(file), len)
+            }
+            None => (Box::new(std::io::empty()), 0),
+        };
+        let stdin_progress = Arc::new(StdinProgress {
+            total,
+            ..Default::default()
+        });
+        let (stdin_tx, stdin_rx) = mpsc::channel();
+        Self::spawn_thread_writer(
+            Box::new(writer),
+            input,
+            stdin_rx,
+            is_pty,
+            stdin_progress.clone(),
+            ctx.clone(),
+        );
 
+        #[cfg(target_os = "linux")]
+        let resources = process.id().map(ResourceMonitor::new);
         Ok(Self {
-            child,
-            stdout: Some(stdout),
-            stderr: Some(stderr),
+            process,
+            output,
+            open_streams,
+            #[cfg(unix)]
+            pty,
+            stdin: interactive.then_some(stdin_tx),
+            stdin_progress,
+            started: ctx.input(|i| i.time),
+            ctx,
+            killed: false,
+            kill_at: None,
+            paused: None,
+            paused_for: Duration::ZERO,
+            timeout: None,
+            timed_out: false,
+            #[cfg(target_os = "linux")]
+            resources,
+            status: None,
         })
     }
 
-    pub fn read(&mut self) -> String {
-        let mut out = String::new();
-        Self::read_stdio(&mut out, &mut self.stdout);
-        Self::read_stdio(&mut out, &mut self.stderr);
-        out
+    pub fn read(&mut self) -> Vec<(Stream, Duration, String)> {
+        let mut lines = vec![];
+        if self.open_streams > 0 {
+            for (stream, time, line) in self.output.try_iter() {
+                match line {
+                    Some(line) => lines.push((stream, time, line)),
+                    None => self.open_streams -= 1,
+                }
+            }
+        }
+        lines
     }
 
     pub fn is_running(&self) -> bool {
-        self.stdout.is_some() || self.stderr.is_some()
+        self.status.is_none()
     }
 
-    pub fn kill(&mut self) {
-        drop(self.child.kill());
-        self.stdout = None;
-        self.stderr = None;
+    /// Whether stdin is still open for [`ChildApp::write_stdin`].
+    pub fn has_stdin(&self) -> bool {
+        self.stdin.is_some()
     }
 
-    fn spawn_thread_reader<R: Read + Send + Sync + 'static>(
-        stdio: R,
+    /// Send text to the child's stdin. Closes stdin if the child stopped reading it.
+    pub fn write_stdin(&mut self, text: &str) {
+        if let Some(stdin) = &self.stdin {
+            if stdin.send(text.to_string()).is_err() {
+                self.stdin = None;
+            }
+        }
+    }
+
+    /// Bytes of the input from [`StdinType`] written so far and in total, while it's being written.
+    pub fn stdin_progress(&self) -> Option<(u64, u64)> {
+        let progress = &self.stdin_progress;
+        let sent = progress.sent.load(Ordering::Relaxed);
+        let writing = progress.total > 0 && sent < progress.total && progress.error.get().is_none();
+        (self.is_running() && writing).then_some((sent, progress.total))
+    }
+
+    /// Error of reading the input from [`StdinType`], which stopped writing it.
+    pub fn stdin_error(&self) -> Option<&io::Error> {
+        self.stdin_progress.error.get()
+    }
+
+    /// Close stdin, so the child reads EOF.
+    pub fn close_stdin(&mut self) {
+        self.stdin = None;
+    }
+
+    pub fn status(&self) -> Option<&RunStatus> {
+        self.status.as_ref()
+    }
+
+    /// Returns the status once, when the child is found to have exited.
+    pub fn update_status(&mut self) -> Option<RunStatus> {
+        // The processes it started may still be running after it exited. Once they are gone
+        // too, the group id can be reused by unrelated processes.
+        #[cfg(unix)]
+        if self.kill_at.is_some() && self.process.try_wait().is_some() && !self.group_exists() {
+            self.kill_at = None;
+        }
+        if let Some(kill_at) = self.kill_at {
+            let now = Instant::now();
+            if now >= kill_at {
+                self.kill_at = None;
+                self.kill_group();
+            } else {
+                self.ctx.request_repaint_after(kill_at - now);
+            }
+        }
+        if let (Some(Duration::ZERO), Some((_, grace_period))) = (self.remaining(), self.timeout) {
+            if !self.timed_out && self.process.try_wait().is_none() {
+                self.timed_out = true;
+                self.stop(Stop::Terminate(grace_period));
+            }
+        }
+
+        if self.status.is_some() {
+            return None;
+        }
+        match self.process.try_wait() {
+            Some(status) => {
+                // The samples miss what happened since the last one and the reaped child has
+                // the exact totals, but not of descendants that are still running
+                #[cfg(target_os = "linux")]
+                let (peak_memory, cpu_time) = {
+                    let usage = self.process.usage();
+                    let resources = self.resources.as_ref();
+                    (
+                        usage
+                            .map(|(memory, _)| memory)
+                            .max(resources.and_then(ResourceMonitor::peak_rss)),
+                        usage
+                            .map(|(_, time)| time)
+                            .max(resources.and_then(ResourceMonitor::cpu_time)),
+                    )
+                };
+                self.status = Some(RunStatus {
+                    exit: if self.timed_out {
+                        ExitStatus::TimedOut
+                    } else if self.killed {
+                        ExitStatus::Killed
+                    } else {
+                        status
+                    },
+                    elapsed: elapsed_since(&self.ctx, self.started),
+                    #[cfg(target_os = "linux")]
+                    peak_memory,
+                    #[cfg(target_os = "linux")]
+                    cpu_time,
+                    #[cfg(not(target_os = "linux"))]
+                    peak_memory: None,
+                    #[cfg(not(target_os = "linux"))]
+                    cpu_time: None,
+                });
+                self.status.clone()
+            }
+            // A child that closed its pipes doesn't wake the GUI when it exits
+            None => {
+                self.ctx.request_repaint_after(Duration::from_millis(100));
+                None
+            }
+        }
+    }
+
+    pub fn stop(&mut self, stop: Stop) {
+        // Don't report a child that already exited on its own as killed
+        if !self.can_stop() || self.process.try_wait().is_some() {
+            return;
+        }
+        // An interrupted child may keep running or exit normally
+        if stop != Stop::Interrupt {
+            self.killed = true;
+            self.stdin_progress.cancelled.store(true, Ordering::Relaxed);
+            self.stdin = None;
+        }
+        self.kill_at = match stop {
+            Stop::Terminate(grace_period) => Some(Instant::now() + grace_period),
+            Stop::Interrupt | Stop::Kill => None,
+        };
+        #[cfg(unix)]
+        {
+            self.signal(match stop {
+                Stop::Interrupt => libc::SIGINT,
+                Stop::Terminate(_) => libc::SIGTERM,
+                Stop::Kill => libc::SIGKILL,
+            });
+            // A paused child handles the signal once it continues
+            if self.paused.is_some() {
+                self.resume();
+            }
+        }
+        #[cfg(not(unix))]
+        self.kill_group();
+        self.ctx.request_repaint();
+    }
+
+    fn kill_group(&mut self) {
+        #[cfg(unix)]
+        self.signal(libc::SIGKILL);
+        #[cfg(not(unix))]
+        {
+            let Process::Child { child, .. } = &mut self.process;
+            drop(child.kill());
+        }
+    }
+
+    /// Whether the child is a process, an app running on a thread can't be stopped or paused.
+    pub fn can_stop(&self) -> bool {
+        self.process.id().is_some()
+    }
+
+    /// Sends `signal` to the child's process group.
+    #[cfg(unix)]
+    pub fn signal(&self, signal: libc::c_int) {
+        let Some(pid) = self.process.id() else {
+            return;
+        };
+        // The child leads its process group, the id isn't reused while the group has processes
+        // SAFETY: kill only sends a signal
+        unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    }
+
+    /// Whether a process of the child's group is still running.
+    #[cfg(unix)]
+    fn group_exists(&self) -> bool {
+        let Some(pid) = self.process.id() else {
+            return false;
+        };
+        // SAFETY: signal 0 only checks that the group exists
+        let result = unsafe { libc::kill(-(pid as libc::pid_t), 0) };
+        result == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+    }
+
+    /// Stops the child with SIGSTOP until [`ChildApp::resume`].
+    #[cfg(unix)]
+    pub fn pause(&mut self) {
+        if self.is_running() && self.can_stop() {
+            self.signal(libc::SIGSTOP);
+            self.paused.get_or_insert(self.ctx.input(|i| i.time));
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn resume(&mut self) {
+        self.signal(libc::SIGCONT);
+        if let Some(paused) = self.paused.take() {
+            self.paused_for += elapsed_since(&self.ctx, paused);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some() && self.is_running()
+    }
+
+    /// Terminate the child once it ran for `timeout`, and kill it after `grace_period`.
+    /// Apps running on a thread don't time out.
+    pub fn set_timeout(&mut self, timeout: Duration, grace_period: Duration) {
+        if self.can_stop() {
+            self.timeout = Some((timeout, grace_period));
+        }
+    }
+
+    /// Time left until the timeout while running. The clock stops while the child is paused.
+    pub fn remaining(&self) -> Option<Duration> {
+        let (timeout, _) = self.timeout.filter(|_| self.is_running())?;
+        let paused = self
+            .paused
+            .map_or(Duration::ZERO, |paused| elapsed_since(&self.ctx, paused));
+        let running = self.elapsed().saturating_sub(self.paused_for + paused);
+        Some(timeout.saturating_sub(running))
+    }
+
+    /// Time since the child was started.
+    pub fn elapsed(&self) -> Duration {
+        elapsed_since(&self.ctx, self.started)
+    }
+
+    /// Resources used by the child and the processes it started, `None` for a thread.
+    #[cfg(target_os = "linux")]
+    pub fn resources(&self) -> Option<&ResourceMonitor> {
+        self.resources.as_ref()
+    }
+
+    /// Feeds `input` and then the interactive lines to the child, so a child that
+    /// doesn't read its stdin can't block the GUI.
+    fn spawn_thread_writer(
+        mut stdin: Box<dyn Write + Send>,
+        mut input: Box<dyn Read + Send>,
+        lines: Receiver<String>,
+        is_pty: bool,
+        progress: Arc<StdinProgress>,
         ctx: egui::Context,
-    ) -> Receiver<Option<String>> {
-        let mut reader = BufReader::new(stdio);
-        let (tx, rx) = mpsc::channel();
-        thread::spawn(move || loop {
-            let mut output = String::new();
-            if let Ok(0) = reader.read_line(&mut output) {
-                // End of output
-                drop(tx.send(None));
+    ) {
+        thread::spawn(move || {
+            let mut buf = vec![0; 64 * 1024];
+            let mut line_start = true;
+            while !progress.cancelled.load(Ordering::Relaxed) {
+                let len = match input.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(len) => len,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        drop(progress.error.set(err));
+                        break;
+                    }
+                };
+                // Fails when the child exits or closes its stdin
+                if stdin.write_all(&buf[..len]).is_err() {
+                    return;
+                }
+                line_start = buf[len - 1] == b'\n';
+                progress.sent.fetch_add(len as u64, Ordering::Relaxed);
                 ctx.request_repaint();
-                break;
             }
-            // Send returns error only if data will never be received
-            if tx.send(Some(output)).is_err() {
-                break;
+            if progress.error.get().is_none() {
+                progress.sent.store(progress.total, Ordering::Relaxed);
             }
             ctx.request_repaint();
+
+            // Ends when the sender is dropped, stdin is closed when this thread returns
+            for line in lines {
+                if stdin
+                    .write_all(line.as_bytes())
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    return;
+                }
+                line_start = line.ends_with('\n');
+            }
+
+            // A terminal stays open, EOF is ^D at the start of a line
+            if is_pty {
+                let eof: &[u8] = if line_start { b"\x04" } else { b"\x04\x04" };
+                drop(stdin.write_all(eof));
+            }
         });
-        rx
     }
 
-    fn read_stdio(output: &mut String, stdio: &mut Option<Receiver<Option<String>>>) {
-        if let Some(receiver) = stdio {
-            for line in receiver.try_iter() {
-                if let Some(line) = line {
-                    output.push_str(&line);
-                } else {
-                    *stdio = None;
+    fn spawn_thread_reader<R: Read + Send + Sync + 'static>(
+        mut stdio: R,
+        stream: Stream,
+        start: Instant,
+        tx: Sender<(Stream, Duration, Option<String>)>,
+        ctx: egui::Context,
+    ) {
+        thread::spawn(move || {
+            let mut buf = vec![0; 8 * 1024];
+            let mut bytes = vec![];
+            loop {
+                // A pty reports an error instead of EOF when the child exits
+                let len = match stdio.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => len,
+                };
+                bytes.extend_from_slice(&buf[..len]);
+                let Some(output) = take_output(&mut bytes) else {
+                    continue;
+                };
+                // Send returns error only if data will never be received
+                if tx.send((stream, start.elapsed(), Some(output))).is_err() {
                     return;
                 }
+                ctx.request_repaint();
             }
+
+            // End of output
+            if !bytes.is_empty() {
+                let output = String::from_utf8_lossy(&bytes).into_owned();
+                drop(tx.send((stream, start.elapsed(), Some(output))));
+            }
+            drop(tx.send((stream, start.elapsed(), None)));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Tell a child running in a pseudo-terminal that the terminal changed size.
+    pub fn resize(&self, _size: TerminalSize) {
+        #[cfg(unix)]
+        if let Some(master) = &self.pty {
+            drop(pty::resize(master, _size));
         }
     }
 }
 
+/// Takes the text that can be shown from the start of `bytes`, leaving a character split
+/// between reads or an unfinished line with a progress bar message.
+#[cfg(not(target_arch = "wasm32"))]
+fn take_output(bytes: &mut Vec<u8>) -> Option<String> {
+    let mut magic = [0; 4];
+    let magic = MAGIC.encode_utf8(&mut magic).as_bytes();
+    let line_end = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let mut end = if bytes[line_end..].windows(magic.len()).any(|w| w == magic) {
+        line_end
+    } else {
+        bytes.len()
+    };
+    if let Err(err) = std::str::from_utf8(&bytes[..end]) {
+        if err.error_len().is_none() {
+            end = err.valid_up_to();
+        }
+    }
+
+    if end == 0 {
+        return None;
+    }
+    let output = String::from_utf8_lossy(&bytes[..end]).into_owned();
+    bytes.drain(..end);
+    Some(output)
+}
+
+fn elapsed_since(ctx: &egui::Context, started: f64) -> Duration {
+    Duration::from_secs_f64((ctx.input(|i| i.time) - started).max(0.0))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn exit_status(status: std::process::ExitStatus) -> ExitStatus {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return ExitStatus::Signal(signal);
+    }
+    // Code is only missing when terminated by a signal
+    ExitStatus::Code(status.code().unwrap_or(-1))
+}
+
+/// Exit status of `child` if it exited, with its peak memory and CPU time including those of
+/// the descendants it waited for. Std doesn't return the resource usage.
+#[cfg(target_os = "linux")]
+fn reap(child: &mut Child) -> Option<(ExitStatus, Option<(u64, Duration)>)> {
+    let mut status = 0;
+    // SAFETY: an all zero rusage is valid
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the call
+    let pid = unsafe {
+        libc::wait4(
+            child.id() as libc::pid_t,
+            &mut status,
+            libc::WNOHANG,
+            &mut usage,
+        )
+    };
+    if pid <= 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    let status = std::os::unix::process::ExitStatusExt::from_raw(status);
+    Some((
+        exit_status(status),
+        // In KiB
+        Some((
+            usage.ru_maxrss as u64 * 1024,
+            time(usage.ru_utime) + time(usage.ru_stime),
+        )),
+    ))
+}
+
+#[cfg(all(not(target_os = "linux"), not(target_arch = "wasm32")))]
+fn reap(child: &mut Child) -> Option<(ExitStatus, Option<(u64, Duration)>)> {
+    let status = child.try_wait().ok().flatten()?;
+    Some((exit_status(status), None))
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 impl Drop for ChildApp {
     fn drop(&mut self) {
-        self.kill();
+        self.stop(Stop::Kill);
+        // Reaped on another thread, so the GUI doesn't wait for it to exit. Elsewhere there is
+        // nothing to reap. A thread can't be stopped, it's left running.
+        #[cfg(unix)]
+        if let (Some(pid), None) = (self.process.id(), self.process.try_wait()) {
+            // SAFETY: waitpid allows a null status
+            thread::spawn(move || unsafe {
+                libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), 0)
+            });
+        }
     }
 }
+
+#[cfg(unix)]
+#[test]
+fn test_exit_status() {
+    let run = |script| {
+        let status = Command::new("sh").args(["-c", script]).status().unwrap();
+        exit_status(status)
+    };
+    assert_eq!(run("exit 0"), ExitStatus::Code(0));
+    assert_eq!(run("exit 3"), ExitStatus::Code(3));
+    assert_eq!(run("kill -9 $$"), ExitStatus::Signal(9));
+}
+
+/// State of a process from `/proc`, like `S` for sleeping or `T` for stopped.
+#[cfg(all(test, target_os = "linux"))]
+fn proc_state(pid: libc::pid_t) -> Option<char> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The name before the state is in parentheses and can contain spaces
+    stat[stat.rfind(')')? + 1..].trim_start().chars().next()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_pause() {
+    use std::os::unix::process::CommandExt;
+
+    let child = Command::new("sleep")
+        .arg("60")
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let pid = child.id() as libc::pid_t;
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        None,
+        false,
+        ctx,
+    )
+    .unwrap();
+
+    let wait_for = |state| {
+        for _ in 0..100 {
+            if proc_state(pid) == Some(state) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    };
+    child.pause();
+    assert!(child.is_paused());
+    assert!(wait_for('T'));
+    child.resume();
+    assert!(!child.is_paused());
+    assert!(wait_for('S'));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout() {
+    use std::os::unix::process::CommandExt;
+
+    let child = Command::new("sleep")
+        .arg("60")
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        None,
+        false,
+        ctx.clone(),
+    )
+    .unwrap();
+    child.set_timeout(Duration::from_secs(2), Duration::ZERO);
+    assert!(child.update_status().is_none());
+
+    let set_time = |time| {
+        let input = egui::RawInput {
+            time: Some(time),
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |_| {});
+    };
+
+    // Time while paused doesn't count
+    child.pause();
+    set_time(5.0);
+    child.resume();
+    assert_eq!(child.remaining(), Some(Duration::from_secs(2)));
+    assert!(child.update_status().is_none());
+
+    set_time(8.0);
+    assert_eq!(child.remaining(), Some(Duration::ZERO));
+    while child.update_status().is_none() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(child.status().unwrap().exit, ExitStatus::TimedOut);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_no_kill_after_group_exited() {
+    use std::os::unix::process::CommandExt;
+
+    let child = Command::new("sleep")
+        .arg("60")
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        None,
+        false,
+        ctx,
+    )
+    .unwrap();
+    child.stop(Stop::Terminate(Duration::from_secs(60)));
+    assert!(child.kill_at.is_some());
+    while child.update_status().is_none() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    // The group id may be reused once it's empty
+    assert!(!child.group_exists());
+    assert!(child.kill_at.is_none());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_usage_of_short_run() {
+    let child = Command::new("sh")
+        .args(["-c", "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done"])
+        .spawn()
+        .unwrap();
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        None,
+        false,
+        ctx,
+    )
+    .unwrap();
+    let status = loop {
+        if let Some(status) = child.update_status() {
+            break status;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    // Taken from the reaped child, not from samples that may have missed the whole run
+    assert!(status.cpu_time.unwrap() > Duration::ZERO);
+    assert!(status.peak_memory.unwrap() > 0);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stop_kills_group() {
+    use std::os::unix::process::CommandExt;
+
+    let mut child = Command::new("sh");
+    child
+        .args(["-c", "sleep 60 & echo $!; wait"])
+        .stdout(Stdio::piped())
+        .process_group(0);
+    let mut child = child.spawn().unwrap();
+    let mut line = String::new();
+    std::io::BufRead::read_line(
+        &mut std::io::BufReader::new(child.stdout.take().unwrap()),
+        &mut line,
+    )
+    .unwrap();
+    let sleep: libc::pid_t = line.trim().parse().unwrap();
+
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        None,
+        false,
+        ctx,
+    )
+    .unwrap();
+    child.stop(Stop::Kill);
+    while child.update_status().is_none() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(child.status().unwrap().exit, ExitStatus::Killed);
+
+    // The grandchild is a zombie until init reaps it
+    let running = || proc_state(sleep).is_some_and(|state| state != 'Z');
+    for _ in 0..100 {
+        if !running() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!running());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_in_process() {
+    let runs = Arc::new(AtomicU64::new(0));
+    let app: InProcessApp = Arc::new(Mutex::new({
+        let runs = runs.clone();
+        move |_: &ArgMatches| {
+            let run = runs.fetch_add(1, Ordering::Relaxed);
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).unwrap();
+            // The test harness captures print!, but not writes to stdout
+            write!(std::io::stdout(), "{run} {line}").unwrap();
+            assert_eq!(run, 0, "second run");
+        }
+    }));
+
+    let ctx = egui::Context::default();
+    for (run, code) in [(0, 0), (1, 101)] {
+        let stdin = Some(StdinType::Text("input\n".into()));
+        let mut child = ChildApp::run_in_process(
+            app.clone(),
+            ArgMatches::default(),
+            stdin,
+            false,
+            ctx.clone(),
+        )
+        .unwrap();
+        assert!(!child.can_stop());
+        while child.update_status().is_none() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(child.status().unwrap().exit, ExitStatus::Code(code));
+
+        let mut output = String::new();
+        while child.open_streams > 0 {
+            for (stream, _, text) in child.read() {
+                if stream == Stream::Stdout {
+                    output += &text;
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Other tests may print while the output is captured
+        assert!(output.contains(&format!("{run} input\n")), "{output:?}");
+    }
+    assert_eq!(runs.load(Ordering::Relaxed), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stop_cancels_stdin() {
+    use std::os::unix::process::CommandExt;
+
+    let child = Command::new("sleep")
+        .arg("60")
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let (_, output) = mpsc::channel();
+    let ctx = egui::Context::default();
+    // Endless input to a writer that never blocks
+    let stdin = Some(StdinType::File("/dev/zero".into()));
+    let mut child = ChildApp::start(
+        Process::from(child),
+        output,
+        0,
+        std::io::sink(),
+        None,
+        stdin,
+        false,
+        ctx,
+    )
+    .unwrap();
+    child.stop(Stop::Kill);
+
+    // The writer thread holds the progress until it returns
+    let progress = child.stdin_progress.clone();
+    for _ in 0..100 {
+        if Arc::strong_count(&progress) == 2 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(Arc::strong_count(&progress), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stdin_writer() {
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = vec![b'x'; 1024 * 1024];
+    let progress = Arc::new(StdinProgress {
+        total: input.len() as u64,
+        ..Default::default()
+    });
+    let (tx, rx) = mpsc::channel();
+    ChildApp::spawn_thread_writer(
+        Box::new(child.stdin.take().unwrap()),
+        Box::new(std::io::Cursor::new(input)),
+        rx,
+        false,
+        progress.clone(),
+        egui::Context::default(),
+    );
+    tx.send("end".into()).unwrap();
+    drop(tx);
+
+    // Reading after the writer started would deadlock if it wrote on this thread
+    let mut output = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output.len(), 1024 * 1024 + 3);
+    assert!(output.ends_with("xend"));
+    assert_eq!(progress.sent.load(Ordering::Relaxed), progress.total);
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_stdin_read_error() {
+    /// Reads as many bytes as it holds, then fails.
+    struct Failing(usize);
+    impl Read for Failing {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0 == 0 {
+                return Err(io::Error::other("disk removed"));
+            }
+            let len = buf.len().min(self.0);
+            self.0 -= len;
+            Ok(len)
+        }
+    }
+
+    let progress = Arc::new(StdinProgress {
+        total: 100,
+        ..Default::default()
+    });
+    let (tx, rx) = mpsc::channel();
+    drop(tx);
+    ChildApp::spawn_thread_writer(
+        Box::new(std::io::sink()),
+        Box::new(Failing(10)),
+        rx,
+        false,
+        progress.clone(),
+        egui::Context::default(),
+    );
+    for _ in 0..100 {
+        if Arc::strong_count(&progress) == 1 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(progress.error.get().unwrap().to_string(), "disk removed");
+    assert_eq!(progress.sent.load(Ordering::Relaxed), 10);
+}
+
+#[test]
+fn test_take_output() {
+    let mut bytes = "50%\r€".as_bytes().to_vec();
+    bytes.pop();
+    assert_eq!(take_output(&mut bytes).as_deref(), Some("50%\r"));
+    assert_eq!(bytes.len(), 2);
+
+    let mut bytes = format!("done\n{MAGIC}1{MAGIC}progress-bar").into_bytes();
+    assert_eq!(take_output(&mut bytes).as_deref(), Some("done\n"));
+    assert_eq!(take_output(&mut bytes), None);
+}
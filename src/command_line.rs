@@ -0,0 +1,426 @@
+use std::fmt::Write;
+
+/// Shell syntax used when displaying a command line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShellSyntax {
+    Posix,
+    PowerShell,
+    Cmd,
+}
+
+impl Default for ShellSyntax {
+    fn default() -> Self {
+        if cfg!(windows) {
+            Self::PowerShell
+        } else {
+            Self::Posix
+        }
+    }
+}
+
+impl ShellSyntax {
+    pub const ALL: [ShellSyntax; 3] = [
+        ShellSyntax::Posix,
+        ShellSyntax::PowerShell,
+        ShellSyntax::Cmd,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShellSyntax::Posix => "sh",
+            ShellSyntax::PowerShell => "PowerShell",
+            ShellSyntax::Cmd => "cmd",
+        }
+    }
+
+    /// Quote a single argument so the shell passes it to the program unchanged.
+    pub fn quote(self, arg: &str) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+=@%:,./".contains(c);
+        match self {
+            ShellSyntax::Posix => {
+                if !arg.is_empty() && arg.chars().all(is_safe) {
+                    arg.to_string()
+                } else {
+                    format!("'{}'", arg.replace('\'', r"'\''"))
+                }
+            }
+            ShellSyntax::PowerShell => {
+                // `@` and `%` have special meaning at the start of an argument,
+                // `,` makes an array of the parts around it
+                if !arg.is_empty() && arg.chars().all(|c| is_safe(c) && !"@%,".contains(c)) {
+                    arg.to_string()
+                } else {
+                    // Single quotes don't expand anything, they are escaped by doubling.
+                    // PowerShell also treats typographic quotes as quotes.
+                    let mut quoted = String::from('\'');
+                    for c in arg.chars() {
+                        if matches!(c, '\'' | '‘' | '’' | '‚' | '‛') {
+                            quoted.push(c);
+                        }
+                        quoted.push(c);
+                    }
+                    quoted.push('\'');
+                    quoted
+                }
+            }
+            ShellSyntax::Cmd => {
+                if !arg.is_empty() && arg.chars().all(|c| is_safe(c) && c != '%') {
+                    return arg.to_string();
+                }
+
+                // Quoting as expected by `CommandLineToArgvW`, see
+                // <https://learn.microsoft.com/en-us/cpp/c-language/parsing-c-command-line-arguments>
+                let mut quoted = String::from('"');
+                let mut backslashes = 0;
+                for c in arg.chars() {
+                    match c {
+                        '\\' => {
+                            backslashes += 1;
+                            continue;
+                        }
+                        // Backslashes before a quote must be escaped too
+                        '"' => quoted.push_str(&"\\".repeat(2 * backslashes + 1)),
+                        _ => quoted.push_str(&"\\".repeat(backslashes)),
+                    }
+                    backslashes = 0;
+                    // In scripts cmd expands variables even inside quotes
+                    if c == '%' {
+                        quoted.push_str("%%");
+                    } else {
+                        quoted.push(c);
+                    }
+                }
+                quoted.push_str(&"\\".repeat(2 * backslashes));
+                quoted.push('"');
+                quoted
+            }
+        }
+    }
+
+    /// Format a whole invocation, including environment variables and working directory.
+    pub fn command_line(
+        self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+        working_dir: Option<&str>,
+    ) -> String {
+        let mut out = String::new();
+
+        match self {
+            ShellSyntax::Posix => {
+                if let Some(dir) = working_dir {
+                    write!(out, "cd {} && ", self.quote(dir)).unwrap();
+                }
+                for (key, value) in env {
+                    write!(out, "{key}={} ", self.quote(value)).unwrap();
+                }
+                out.push_str(&self.quote(program));
+            }
+            ShellSyntax::PowerShell => {
+                if let Some(dir) = working_dir {
+                    writeln!(out, "Set-Location {}", self.quote(dir)).unwrap();
+                }
+                for (key, value) in env {
+                    writeln!(out, "$env:{key} = {}", self.quote(value)).unwrap();
+                }
+                // Quoted program names need the call operator
+                write!(out, "& {}", self.quote(program)).unwrap();
+            }
+            ShellSyntax::Cmd => {
+                if let Some(dir) = working_dir {
+                    writeln!(out, "cd /d {}", self.quote(dir)).unwrap();
+                }
+                for (key, value) in env {
+                    // Quoting the whole assignment keeps trailing spaces out of the value
+                    writeln!(out, "set \"{key}={}\"", value.replace('%', "%%")).unwrap();
+                }
+                out.push_str(&self.quote(program));
+            }
+        }
+
+        for arg in args {
+            out.push(' ');
+            out.push_str(&self.quote(arg));
+        }
+        out
+    }
+
+    /// Split a pasted command line into the program and its arguments, undoing quoting.
+    /// Only the last command is used, so `cd dir && app --flag` gives `["app", "--flag"]`.
+    /// Returns `None` if a quote isn't closed.
+    pub fn split(self, text: &str) -> Option<Vec<String>> {
+        if self == ShellSyntax::Cmd {
+            return split_cmd(text);
+        }
+
+        let mut tokens = vec![];
+        let mut token: Option<String> = None;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (self, c) {
+                (_, ' ' | '\t' | '\r') => tokens.extend(token.take()),
+                (_, '\n' | ';' | '|') | (ShellSyntax::Posix, '&') => {
+                    // Only the last command is used
+                    token = None;
+                    tokens.clear();
+                }
+                // Call operator
+                (ShellSyntax::PowerShell, '&') if token.is_none() => {}
+                (ShellSyntax::Posix, '\\') => match chars.next() {
+                    // Line continuation
+                    Some('\n') | None => {}
+                    Some(c) => token.get_or_insert_with(String::new).push(c),
+                },
+                (ShellSyntax::Posix, '\'') => {
+                    let token = token.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '\'' => break,
+                            c => token.push(c),
+                        }
+                    }
+                }
+                (ShellSyntax::Posix, '"') => {
+                    let token = token.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            '\\' => match chars.next()? {
+                                '\n' => {}
+                                c @ ('$' | '`' | '"' | '\\') => token.push(c),
+                                c => {
+                                    token.push('\\');
+                                    token.push(c);
+                                }
+                            },
+                            c => token.push(c),
+                        }
+                    }
+                }
+                (ShellSyntax::PowerShell, '`') => match chars.next() {
+                    Some('\n') | None => {}
+                    Some(c) => token
+                        .get_or_insert_with(String::new)
+                        .push(powershell_escape(c)),
+                },
+                (ShellSyntax::PowerShell, '\'') => {
+                    let token = token.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '\'' if chars.peek() == Some(&'\'') => {
+                                chars.next();
+                                token.push('\'');
+                            }
+                            '\'' => break,
+                            c => token.push(c),
+                        }
+                    }
+                }
+                (ShellSyntax::PowerShell, '"') => {
+                    let token = token.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '"' if chars.peek() == Some(&'"') => {
+                                chars.next();
+                                token.push('"');
+                            }
+                            '"' => break,
+                            '`' => token.push(powershell_escape(chars.next()?)),
+                            c => token.push(c),
+                        }
+                    }
+                }
+                (_, c) => token.get_or_insert_with(String::new).push(c),
+            }
+        }
+        tokens.extend(token);
+
+        if self == ShellSyntax::Posix {
+            // Drop environment variable assignments before the program
+            let assignments = tokens
+                .iter()
+                .take_while(|t| {
+                    t.split_once('=').is_some_and(|(name, _)| {
+                        !name.is_empty()
+                            && !name.starts_with(|c: char| c.is_ascii_digit())
+                            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    })
+                })
+                .count();
+            tokens.drain(..assignments);
+        }
+
+        Some(tokens)
+    }
+}
+
+fn powershell_escape(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        c => c,
+    }
+}
+
+/// Splitting as done by `CommandLineToArgvW` with cmd's `^` escapes on top.
+fn split_cmd(text: &str) -> Option<Vec<String>> {
+    let mut tokens = vec![];
+    let mut token: Option<String> = None;
+    let mut in_quotes = false;
+    let mut backslashes = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+
+        if c == '"' {
+            let current = token.get_or_insert_with(String::new);
+            current.push_str(&"\\".repeat(backslashes / 2));
+            if backslashes % 2 == 1 {
+                current.push('"');
+            } else {
+                in_quotes = !in_quotes;
+            }
+            backslashes = 0;
+            continue;
+        }
+        if backslashes > 0 {
+            token
+                .get_or_insert_with(String::new)
+                .push_str(&"\\".repeat(backslashes));
+            backslashes = 0;
+        }
+
+        match c {
+            ' ' | '\t' | '\r' if !in_quotes => tokens.extend(token.take()),
+            '\n' => {
+                in_quotes = false;
+                token = None;
+                tokens.clear();
+            }
+            '&' | '|' if !in_quotes => {
+                token = None;
+                tokens.clear();
+            }
+            '^' if !in_quotes => {
+                if let Some(c) = chars.next().filter(|&c| c != '\n') {
+                    token.get_or_insert_with(String::new).push(c);
+                }
+            }
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                token.get_or_insert_with(String::new).push('%');
+            }
+            c => token.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    if backslashes > 0 {
+        token
+            .get_or_insert_with(String::new)
+            .push_str(&"\\".repeat(backslashes));
+    }
+    tokens.extend(token);
+    Some(tokens)
+}
+
+#[test]
+fn test_quote() {
+    for (arg, posix, powershell, cmd) in [
+        ("simple", "simple", "simple", "simple"),
+        (
+            "--long=value",
+            "--long=value",
+            "--long=value",
+            "--long=value",
+        ),
+        ("", "''", "''", r#""""#),
+        (
+            "with space",
+            "'with space'",
+            "'with space'",
+            r#""with space""#,
+        ),
+        ("it's", r"'it'\''s'", "'it''s'", r#""it's""#),
+        (
+            r#"say "hi""#,
+            r#"'say "hi"'"#,
+            r#"'say "hi"'"#,
+            r#""say \"hi\"""#,
+        ),
+        ("$HOME", "'$HOME'", "'$HOME'", r#""$HOME""#),
+        ("%PATH%", "%PATH%", "'%PATH%'", r#""%%PATH%%""#),
+        (r"C:\dir\", r"'C:\dir\'", r"'C:\dir\'", r#""C:\dir\\""#),
+        ("a,b", "a,b", "'a,b'", "a,b"),
+    ] {
+        assert_eq!(ShellSyntax::Posix.quote(arg), posix);
+        assert_eq!(ShellSyntax::PowerShell.quote(arg), powershell);
+        assert_eq!(ShellSyntax::Cmd.quote(arg), cmd);
+    }
+}
+
+#[test]
+fn test_command_line() {
+    let args = ["--name".to_string(), "a b".to_string()];
+    let env = [("KEY".to_string(), "x y".to_string())];
+
+    assert_eq!(
+        ShellSyntax::Posix.command_line("app", &args, &env, Some("/tmp/dir")),
+        "cd /tmp/dir && KEY='x y' app --name 'a b'"
+    );
+    assert_eq!(
+        ShellSyntax::PowerShell.command_line("app", &args, &env, None),
+        "$env:KEY = 'x y'\n& app --name 'a b'"
+    );
+    assert_eq!(
+        ShellSyntax::Cmd.command_line("app", &args, &env, None),
+        "set \"KEY=x y\"\napp --name \"a b\""
+    );
+}
+
+#[test]
+fn test_split() {
+    let program = "app".to_string();
+    let args = vec![
+        "--name".to_string(),
+        "a b".to_string(),
+        "it's".to_string(),
+        r#"say "hi""#.to_string(),
+        String::new(),
+        "%PATH%".to_string(),
+        r"C:\dir\".to_string(),
+        "a,b".to_string(),
+    ];
+    let env = [("KEY".to_string(), "x y".to_string())];
+
+    for shell in ShellSyntax::ALL {
+        let line = shell.command_line(&program, &args, &env, Some("/tmp/some dir"));
+        let mut expected = vec![program.clone()];
+        expected.extend(args.iter().cloned());
+        assert_eq!(shell.split(&line), Some(expected), "{line}");
+    }
+
+    assert_eq!(
+        ShellSyntax::Posix.split("app --a \\\n  \"x\\\"y\""),
+        Some(vec!["app".into(), "--a".into(), r#"x"y"#.into()])
+    );
+    assert_eq!(ShellSyntax::Posix.split("app 'unclosed"), None);
+    assert_eq!(
+        ShellSyntax::PowerShell.split("app \"a`tb\""),
+        Some(vec!["app".into(), "a\tb".into()])
+    );
+    assert_eq!(
+        ShellSyntax::Cmd.split(r#"app a^&b "c&d""#),
+        Some(vec!["app".into(), "a&b".into(), "c&d".into()])
+    );
+}
@@ -1,34 +1,31 @@
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{Command, Id};
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExecutionError {
     #[error("Internal io error: {0}")]
     IoError(#[from] std::io::Error),
-    #[error("Internal error: no name in validation")]
-    NoValidationName,
     #[error("Internal match error: {0}")]
     MatchError(clap::Error),
     #[cfg(not(target_arch = "wasm32"))]
     #[error("Internal error: no child stdout or stderr")]
     NoStdoutOrStderr,
-    #[error("Validation error in {}: '{}'", .name, .message)]
-    ValidationError { name: String, message: String },
+    /// Error caused by invalid input that the user can fix.
+    #[error("{}", clap_message(.0))]
+    ValidationError(clap::Error),
     #[error("{0}")]
     GuiError(String),
 }
 
 impl From<clap::Error> for ExecutionError {
     fn from(err: clap::Error) -> Self {
-        match clap::Error::kind(&err) {
-            clap::error::ErrorKind::ValueValidation => {
-                if let Some((kind, value)) = err.context().next() {
-                    Self::ValidationError {
-                        name: kind.to_string(),
-                        message: value.to_string(),
-                    }
-                } else {
-                    Self::NoValidationName
-                }
-            }
-            _ => Self::MatchError(err),
+        match err.kind() {
+            ErrorKind::DisplayHelp
+            | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+            | ErrorKind::DisplayVersion
+            | ErrorKind::Io
+            | ErrorKind::Format => Self::MatchError(err),
+            _ => Self::ValidationError(err),
         }
     }
 }
@@ -44,3 +41,82 @@ impl From<&str> for ExecutionError {
         Self::GuiError(str.to_string())
     }
 }
+
+/// Clap's message without the "error: " prefix, usage and tips.
+pub fn clap_message(err: &clap::Error) -> String {
+    let rendered = err.render().to_string();
+    let first = rendered.lines().next().unwrap_or_default();
+    first.strip_prefix("error: ").unwrap_or(first).to_string()
+}
+
+/// Args the error is about, as clap displays them, e.g. `--name <NAME>`,
+/// or `<--a|--b <B>>` for a group.
+pub fn involved_args(err: &clap::Error) -> Vec<String> {
+    let mut args = vec![];
+    for kind in [ContextKind::InvalidArg, ContextKind::PriorArg] {
+        match err.get(kind) {
+            Some(ContextValue::String(arg)) => args.push(arg.clone()),
+            Some(ContextValue::Strings(more)) => args.extend(more.iter().cloned()),
+            _ => {}
+        }
+    }
+    args
+}
+
+/// Ids of the args of `cmd` that clap displays as one of `displayed` in its errors.
+/// Errors only have the displayed args, so each arg and group of `cmd` is displayed
+/// the same way to find them. A group stands for all of its args.
+pub fn displayed_arg_ids(cmd: &Command, displayed: &[String]) -> Vec<String> {
+    let mut ids: Vec<String> = cmd
+        .get_arguments()
+        .filter(|arg| displayed.contains(&arg.to_string()))
+        .map(|arg| arg.get_id().to_string())
+        .collect();
+
+    for group in cmd.get_groups() {
+        let args = group_args(cmd, group.get_id());
+        let shown = args
+            .iter()
+            .filter_map(|id| cmd.get_arguments().find(|arg| arg.get_id() == id))
+            .map(|arg| match arg.get_value_names() {
+                // Positionals are shown with their value names without brackets
+                _ if !arg.is_positional() => arg.to_string(),
+                Some([name]) => name.to_string(),
+                Some(names) => names
+                    .iter()
+                    .map(|n| format!("<{n}>"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                None => arg.get_id().to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("|");
+        let shown = [format!("<{shown}>"), group.get_id().to_string()];
+        if displayed.iter().any(|d| shown.contains(d)) {
+            ids.extend(args.iter().map(ToString::to_string));
+        }
+    }
+    ids
+}
+
+/// Ids of the args in a group and in the groups it contains.
+fn group_args(cmd: &Command, group: &Id) -> Vec<Id> {
+    let mut groups = vec![group.clone()];
+    let mut args = vec![];
+    while let Some(group) = groups.pop() {
+        let Some(group) = cmd.get_groups().find(|g| *g.get_id() == group) else {
+            continue;
+        };
+        for id in group.get_args() {
+            if args.contains(id) {
+                continue;
+            }
+            if cmd.get_arguments().any(|arg| arg.get_id() == id) {
+                args.push(id.clone());
+            } else {
+                groups.push(id.clone());
+            }
+        }
+    }
+    args
+}
@@ -0,0 +1,240 @@
+//! Saving the output to files.
+
+use crate::output::{ansi_color_to_egui, OutputType, OutputView, Stream};
+use crate::terminal::{self, Style};
+use eframe::egui::Color32;
+use linkify::{LinkFinder, LinkKind};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Format of saved output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExportFormat {
+    /// Text without ANSI codes.
+    Text,
+    /// Text with ANSI codes, like the app printed it.
+    Ansi,
+    /// Standalone page with the colors of the output.
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 3] = [Self::Text, Self::Ansi, Self::Html];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Ansi => "log",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Colors of the page for [`ExportFormat::Html`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PageColors {
+    pub text: Color32,
+    pub background: Color32,
+    pub link: Color32,
+}
+
+/// Lines of output in `format`. Progress bars are saved in their last state.
+pub(crate) fn export<'a>(
+    lines: impl IntoIterator<Item = &'a OutputType>,
+    format: ExportFormat,
+    view: &OutputView,
+    colors: PageColors,
+) -> String {
+    let mut out = String::new();
+    if format == ExportFormat::Html {
+        out += &format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <style>body {{ color: {}; background: {}; }} a {{ color: {}; }}</style>\n\
+             </head>\n<body>\n<pre>\n",
+            css_color(colors.text),
+            css_color(colors.background),
+            css_color(colors.link),
+        );
+    }
+
+    for line in lines {
+        match (line, format) {
+            (OutputType::Text(text, ..), ExportFormat::Ansi) => out += text,
+            (OutputType::Text(text, ..), ExportFormat::Text) => {
+                out.extend(terminal::spans(text).into_iter().map(|(_, text)| text))
+            }
+            (OutputType::Text(text, stream, _), ExportFormat::Html) => {
+                let color = (*stream == Stream::Stderr).then_some(view.stderr_color);
+                for (style, text) in terminal::spans(text) {
+                    html_span(&mut out, text, style, color, view, colors);
+                }
+            }
+            (OutputType::ProgressBar(desc, value), ExportFormat::Html) => {
+                out += &format!(
+                    "<progress value=\"{value}\"></progress> {}\n",
+                    escape_html(desc.trim_end_matches('\n'))
+                );
+            }
+            (OutputType::ProgressBar(desc, value), _) => {
+                out += &format!("{} {:.0}%\n", desc.trim_end_matches('\n'), value * 100.0);
+            }
+        }
+    }
+
+    if format == ExportFormat::Html {
+        out += "</pre>\n</body>\n</html>\n";
+    }
+    out
+}
+
+/// Appends `text` in a span styled like in the output, `color` is used where it doesn't set one.
+fn html_span(
+    out: &mut String,
+    text: &str,
+    style: Style,
+    color: Option<Color32>,
+    view: &OutputView,
+    colors: PageColors,
+) {
+    let mut fg = style
+        .fg
+        .map(|fg| ansi_color_to_egui(fg, &view.palette))
+        .or(color);
+    let mut bg = style.bg.map(|bg| ansi_color_to_egui(bg, &view.palette));
+    if style.inverse {
+        (fg, bg) = (
+            Some(bg.unwrap_or(colors.background)),
+            Some(fg.unwrap_or(colors.text)),
+        );
+    }
+
+    let mut css = vec![];
+    if let Some(fg) = fg {
+        css.push(format!("color: {}", css_color(fg)));
+    }
+    if let Some(bg) = bg {
+        css.push(format!("background: {}", css_color(bg)));
+    }
+    for (set, property) in [
+        (style.bold, "font-weight: bold"),
+        (style.faint, "opacity: 0.6"),
+        (style.italic, "font-style: italic"),
+    ] {
+        if set {
+            css.push(property.to_string());
+        }
+    }
+    let lines: Vec<_> = [
+        (style.underline, "underline"),
+        (style.strikethrough, "line-through"),
+    ]
+    .into_iter()
+    .filter_map(|(set, line)| set.then_some(line))
+    .collect();
+    if !lines.is_empty() {
+        css.push(format!("text-decoration: {}", lines.join(" ")));
+    }
+
+    if !css.is_empty() {
+        *out += &format!("<span style=\"{}\">", css.join("; "));
+    }
+    for span in LinkFinder::new().spans(text) {
+        let text = escape_html(span.as_str());
+        match span.kind() {
+            Some(LinkKind::Url) => *out += &format!("<a href=\"{text}\">{text}</a>"),
+            Some(LinkKind::Email) => *out += &format!("<a href=\"mailto:{text}\">{text}</a>"),
+            Some(_) | None => *out += &text,
+        }
+    }
+    if !css.is_empty() {
+        *out += "</span>";
+    }
+}
+
+fn css_color(color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Asks where to save the output and writes `text()` there.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save(format: ExportFormat, text: impl FnOnce() -> String) -> io::Result<()> {
+    let extension = format.extension();
+    let path = rfd::FileDialog::new()
+        .set_file_name(format!("output.{extension}"))
+        .add_filter(extension, &[extension])
+        .save_file();
+    match path {
+        Some(path) => std::fs::write(path, text()),
+        None => Ok(()),
+    }
+}
+
+/// File in [`crate::Settings::output_log_dir`] getting the output of a run as it's printed,
+/// with ANSI codes and without progress bars.
+#[derive(Debug)]
+pub(crate) struct OutputLog(BufWriter<File>);
+
+impl OutputLog {
+    /// Creates a new file in `dir` named after the program and the current time.
+    pub fn create(dir: &Path, program: &str) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let file = File::create(dir.join(format!("{program}-{millis}.log")))?;
+        Ok(Self(BufWriter::new(file)))
+    }
+
+    /// Errors are ignored, the output is still shown.
+    pub fn write(&mut self, text: &str) {
+        let _ = self.0.write_all(text.as_bytes());
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.0.flush();
+    }
+}
+
+#[test]
+fn test_export() {
+    use std::time::Duration;
+
+    let lines = [
+        OutputType::Text(
+            "\x1b[0;1;31mred\x1b[0m <https://example.com>\n".into(),
+            Stream::Stdout,
+            Duration::ZERO,
+        ),
+        OutputType::ProgressBar("bar\n".into(), 0.5),
+    ];
+    let view = OutputView::default();
+    let colors = PageColors {
+        text: Color32::WHITE,
+        background: Color32::BLACK,
+        link: Color32::BLUE,
+    };
+    let export = |format| export(&lines, format, &view, colors);
+
+    assert_eq!(
+        export(ExportFormat::Text),
+        "red <https://example.com>\nbar 50%\n"
+    );
+    assert!(export(ExportFormat::Ansi).starts_with("\x1b[0;1;31mred"));
+    let html = export(ExportFormat::Html);
+    assert!(html.contains("font-weight: bold\">red</span>"));
+    assert!(html.contains("&lt;<a href=\"https://example.com\">https://example.com</a>&gt;"));
+    assert!(html.contains("<progress value=\"0.5\"></progress> bar\n"));
+}
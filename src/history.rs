@@ -0,0 +1,138 @@
+use crate::{buffer::OutputBuffer, child_app::StdinType, output::RunStatus};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Everything needed to start the child again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunParams {
+    /// Arguments without the binary name, as from [`crate::app_state::AppState::get_cmd_args`].
+    pub args: Vec<String>,
+    pub env: Option<Vec<(String, String)>>,
+    pub stdin: Option<StdinType>,
+    pub working_dir: Option<String>,
+}
+
+/// A past or running invocation shown in the history panel.
+#[derive(Debug)]
+pub struct Run {
+    pub params: RunParams,
+    /// Wall-clock time the run was started.
+    pub started: SystemTime,
+    /// `None` while the child is running.
+    pub status: Option<RunStatus>,
+    /// Captured output. Empty for the latest run, its output is in [`crate::output::Output`].
+    pub output: OutputBuffer,
+}
+
+impl Run {
+    pub fn new(params: RunParams) -> Self {
+        Self {
+            params,
+            started: now(),
+            status: None,
+            output: OutputBuffer::default(),
+        }
+    }
+}
+
+/// The current time, [`SystemTime::now`] panics on the web.
+pub fn now() -> SystemTime {
+    #[cfg(target_arch = "wasm32")]
+    {
+        UNIX_EPOCH + std::time::Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        SystemTime::now()
+    }
+}
+
+/// Format the local time of day like `14:05:09`, in UTC where the time zone isn't known.
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    format_time_of_day(seconds + utc_offset(seconds))
+}
+
+fn format_time_of_day(seconds: i64) -> String {
+    let seconds = seconds.rem_euclid(24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Seconds the local time zone is ahead of UTC at `time`, in seconds since the epoch.
+fn utc_offset(time: i64) -> i64 {
+    #[cfg(unix)]
+    {
+        // SAFETY: an all zero `tm` is valid
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        // SAFETY: both pointers are valid for the call
+        if unsafe { libc::localtime_r(&(time as libc::time_t), &mut tm) }.is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let date = js_sys::Date::new(&(time as f64 * 1000.0).into());
+        -(date.get_timezone_offset() * 60.0) as i64
+    }
+    #[cfg(not(any(unix, target_arch = "wasm32")))]
+    {
+        let _ = time;
+        0
+    }
+}
+
+/// Format seconds like `1.5s` or `2m 03s`.
+pub fn format_duration(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        let seconds = seconds as u64;
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+/// Format a byte count like `512 B` or `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(0.34), "0.3s");
+    assert_eq!(format_duration(59.0), "59.0s");
+    assert_eq!(format_duration(123.4), "2m 03s");
+}
+
+#[test]
+fn test_format_time() {
+    assert_eq!(format_time_of_day(3723), "01:02:03");
+    assert_eq!(format_time_of_day(86405), "00:00:05");
+    assert_eq!(format_time_of_day(-1), "23:59:59");
+    assert_eq!(format_time(now()).len(), 8);
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+    assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+}
@@ -30,34 +30,71 @@
 /// App state
 pub mod app_state;
 mod arg_state;
+mod buffer;
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+mod capture;
 mod child_app;
+mod command_line;
 mod error;
+mod export;
+mod history;
 #[cfg(target_arch = "wasm32")]
 /// Logger that outputs all logs to the gui output.
 pub mod logger;
 /// Additional options for output like progress bars.
 pub mod output;
+/// Saved values of the form.
+pub mod preset;
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+mod pty;
+#[cfg(target_os = "linux")]
+mod resources;
+mod search;
 /// Settings
 pub mod settings;
+mod terminal;
 
 use app_state::AppState;
-use child_app::{ChildApp, StdinType};
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+use child_app::InProcessApp;
+#[cfg(not(target_arch = "wasm32"))]
+use child_app::Stop;
+use child_app::{ChildApp, StdinType, TerminalSize};
 use clap::{ArgMatches, Command, CommandFactory, FromArgMatches};
+use command_line::ShellSyntax;
 use eframe::{
-    egui::{self, Button, Color32, Context, FontData, FontDefinitions, Grid, Style, TextEdit, Ui},
+    egui::{
+        self, Align, Button, ComboBox, Context, FontData, FontDefinitions, Grid, Layout, RichText,
+        Style, TextEdit, Ui,
+    },
     CreationContext, Frame,
 };
 use error::ExecutionError;
-use output::Output;
+use export::OutputLog;
+use history::{format_bytes, format_duration, format_time, Run, RunParams};
+use output::{show_output, show_status, ExitStatus, OnRunFinished, Output, OutputView};
+pub use preset::Preset;
 #[cfg(not(target_arch = "wasm32"))]
 use rfd::FileDialog;
-pub use settings::{Localization, Settings};
-use std::{borrow::Cow, hash::Hash};
+pub use settings::{Localization, Palette, Settings, Theme};
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+use std::sync::{Arc, Mutex};
+use std::{borrow::Cow, ffi::OsString, hash::Hash};
 #[cfg(target_arch = "wasm32")]
 use std::{future::Future, sync::Arc, task::Poll};
 
 #[cfg(not(target_arch = "wasm32"))]
 const CHILD_APP_ENV_VAR: &str = "KLASK_CHILD_APP";
+/// Key of the user's presets in eframe's storage.
+const PRESETS_KEY: &str = "klask_presets";
+/// Key of the form from the last session in eframe's storage.
+const SESSION_KEY: &str = "klask_session";
+/// Key of the tab from the last session in eframe's storage.
+const TAB_KEY: &str = "klask_tab";
+/// Key of the name of the selected theme in eframe's storage.
+const THEME_KEY: &str = "klask_theme";
+/// Longest timeout that can be set in the GUI, a week.
+const MAX_TIMEOUT_SECONDS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
 
 /// Call with a [`Command`] and a closure that contains the code that would normally be in `main`.
 /// ```no_run
@@ -79,45 +116,97 @@ pub fn run_app_native(app: Command, settings: Settings, f: impl FnOnce(&ArgMatch
 
         f(&matches);
     } else {
-        // During validation we don't pass in a binary name
-        let app = app.no_binary_name(true);
-        let app_name = app.get_name().to_string();
-
-        // eframe::run_native requires that Box::new(klask) has 'static
-        // lifetime, so we must leak here. But it never returns (return value !)
-        // so it should be ok.
-        let localization = Box::leak(Box::new(settings.localization));
-
-        let mut klask = Klask {
-            state: AppState::new(&app, localization, settings.prefer_long_about),
-            tab: Tab::Arguments,
-            env: settings.enable_env.map(|desc| (desc, vec![])),
-            stdin: settings
-                .enable_stdin
-                .map(|desc| (desc, StdinType::Text(String::new()))),
-            working_dir: settings
-                .enable_working_dir
-                .map(|desc| (desc, String::new())),
-            output: Output::None,
-            app,
-            custom_font: settings.custom_font,
-            localization,
-            style: settings.style,
-            platform_state: Native {},
+        let native = Native {
+            #[cfg(unix)]
+            in_process: None,
         };
-        let native_options = eframe::NativeOptions::default();
-        eframe::run_native(
-            app_name.as_str(),
-            native_options,
-            Box::new(|cc| {
-                klask.setup(cc);
-                Box::new(klask)
-            }),
-        )
-        .unwrap();
+        run_native_gui(app, settings, native);
     }
 }
 
+/// Shows the GUI, runs are started as set in `platform_state`.
+#[cfg(not(target_arch = "wasm32"))]
+fn run_native_gui(app: Command, settings: Settings, platform_state: Native) {
+    // During validation we don't pass in a binary name
+    let app = app.no_binary_name(true);
+    let app_name = app.get_name().to_string();
+
+    // eframe::run_native requires that Box::new(klask) has 'static
+    // lifetime, so we must leak here. But it never returns (return value !)
+    // so it should be ok.
+    let localization = Box::leak(Box::new(settings.localization));
+
+    // An app on a thread shares the environment, working directory and stdio of the GUI,
+    // and can't be stopped, so the settings for those are left out of the GUI
+    #[cfg(unix)]
+    let in_process = platform_state.in_process.is_some();
+    #[cfg(not(unix))]
+    let in_process = false;
+
+    let mut klask = Klask {
+        state: AppState::new(&app, localization, settings.prefer_long_about),
+        tab: Tab::Arguments,
+        env: settings
+            .enable_env
+            .filter(|_| !in_process)
+            .map(|desc| (desc, vec![])),
+        stdin: settings
+            .enable_stdin
+            .map(|desc| (desc, StdinType::Text(String::new()))),
+        working_dir: settings
+            .enable_working_dir
+            .filter(|_| !in_process)
+            .map(|desc| (desc, String::new())),
+        output: Output::None,
+        interactive_stdin: settings.interactive_stdin.then(String::new),
+        terminal: (cfg!(unix) && settings.pty && !in_process).then_some((80, 24)),
+        output_log_dir: settings.output_log_dir,
+        stop_grace_period: settings.stop_grace_period,
+        timeout: (!in_process).then(|| timeout_setting(settings.timeout)),
+        output_view: OutputView::new(
+            settings.palette,
+            settings.stderr_color,
+            settings.success_color,
+        ),
+        program: std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| app_name.clone()),
+        app,
+        shell: ShellSyntax::default(),
+        import: (String::new(), None),
+        builtin_presets: settings.presets,
+        presets: vec![],
+        preset_name: String::new(),
+        remember_session: settings.remember_session,
+        forget_args: settings.forget_args,
+        forget_env: settings.forget_env,
+        forget_stdin: settings.forget_stdin,
+        history: vec![],
+        max_history: settings.max_history,
+        max_output_lines: settings.max_output_lines,
+        shown_run: None,
+        on_run_finished: settings.on_run_finished,
+        theme: (!settings.follow_system_theme && !settings.themes.is_empty()).then_some(0),
+        themes: settings.themes,
+        applied_theme: None,
+        custom_font: settings.custom_font,
+        localization,
+        style: settings.style,
+        platform_state,
+    };
+    let native_options = eframe::NativeOptions::default();
+    eframe::run_native(
+        app_name.as_str(),
+        native_options,
+        Box::new(|cc| {
+            klask.setup(cc);
+            Box::new(klask)
+        }),
+    )
+    .unwrap();
+}
+
 /// Can be used with a struct deriving [`clap::Parser`]. Call with a closure that contains the code that would normally be in `main`.
 /// It's just a wrapper over [`run_app_native`].
 /// ```no_run
@@ -146,6 +235,68 @@ where
     });
 }
 
+/// Like [`run_app_native`], but the closure runs on a thread of the GUI process instead of
+/// starting the binary again. Use it where that doesn't work, like in sandboxes or when the
+/// binary is replaced while the GUI runs.
+///
+/// While the closure runs, the stdin, stdout and stderr of the whole process are redirected
+/// to the GUI, so prints from other threads show up in the output too. Progress bars from
+/// [`output::progress_bar`] work as usual. The closure can't be stopped, paused or timed out,
+/// so the GUI has no buttons for that and no timeout. [`Settings::enable_env`],
+/// [`Settings::enable_working_dir`], [`Settings::pty`] and [`Settings::timeout`] are ignored.
+///
+/// Processes the closure starts inherit the redirected stdio, so the output only ends once
+/// they exit. Calling [`std::process::exit`] in the closure exits the GUI too.
+/// ```no_run
+/// # use clap::{Command, arg};
+/// # use klask::Settings;
+/// let app = Command::new("Example").arg(arg!(--debug <VALUE>).short('d'));
+/// let mut runs = 0;
+/// klask::run_app_in_process(app, Settings::default(), move |matches| {
+///    runs += 1;
+///    println!("{runs}: {:?}", matches.try_contains_id("debug"))
+/// });
+/// ```
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+pub fn run_app_in_process(
+    app: Command,
+    settings: Settings,
+    f: impl FnMut(&ArgMatches) + Send + 'static,
+) {
+    let in_process: InProcessApp = Arc::new(Mutex::new(f));
+    let native = Native {
+        in_process: Some(in_process),
+    };
+    run_native_gui(app, settings, native);
+}
+
+/// Like [`run_derived_native`], but a wrapper over [`run_app_in_process`].
+/// ```no_run
+/// # use clap::Parser;
+/// # use klask::Settings;
+/// #[derive(Parser)]
+/// struct Example {
+///     #[arg(short)]
+///     debug: bool,
+/// }
+///
+/// klask::run_derived_in_process::<Example, _>(Settings::default(), |example|{
+///     println!("{}", example.debug);
+/// });
+/// ```
+#[cfg(all(unix, not(target_arch = "wasm32")))]
+pub fn run_derived_in_process<C, F>(settings: Settings, mut f: F)
+where
+    C: CommandFactory + FromArgMatches,
+    F: FnMut(C) + Send + 'static,
+{
+    run_app_in_process(C::command(), settings, move |m| {
+        let matches = C::from_arg_matches(m)
+            .expect("Internal error, C::from_arg_matches should always succeed");
+        f(matches);
+    });
+}
+
 /// Call with a [`Command`] and a closure that contains the code that would normally be in `main`.
 /// Slightly more complicated to use then [`run_app_native`] because async closures don't exist yet.
 /// ```no_run
@@ -193,7 +344,35 @@ where
             .enable_working_dir
             .map(|desc| (desc, String::new())),
         output: Output::None,
+        interactive_stdin: None,
+        terminal: None,
+        output_log_dir: None,
+        stop_grace_period: settings.stop_grace_period,
+        timeout: Some(timeout_setting(settings.timeout)),
+        output_view: OutputView::new(
+            settings.palette,
+            settings.stderr_color,
+            settings.success_color,
+        ),
+        program: app_name.clone(),
         app,
+        shell: ShellSyntax::default(),
+        import: (String::new(), None),
+        builtin_presets: settings.presets,
+        presets: vec![],
+        preset_name: String::new(),
+        remember_session: settings.remember_session,
+        forget_args: settings.forget_args,
+        forget_env: settings.forget_env,
+        forget_stdin: settings.forget_stdin,
+        history: vec![],
+        max_history: settings.max_history,
+        max_output_lines: settings.max_output_lines,
+        shown_run: None,
+        on_run_finished: settings.on_run_finished,
+        theme: (!settings.follow_system_theme && !settings.themes.is_empty()).then_some(0),
+        themes: settings.themes,
+        applied_theme: None,
         custom_font: settings.custom_font,
         localization,
         style: settings.style,
@@ -279,7 +458,11 @@ where
 
 /// Platform specific state for Klask on native.
 #[cfg(not(target_arch = "wasm32"))]
-struct Native {}
+struct Native {
+    /// Set by [`run_app_in_process`], the app runs on a thread instead of a new process.
+    #[cfg(unix)]
+    in_process: Option<InProcessApp>,
+}
 /// Platform specific state for Klask on wasm.
 #[cfg(target_arch = "wasm32")]
 struct Wasm<F> {
@@ -313,9 +496,16 @@ where
 /// Common parts of wasm and non wasm klask
 trait KlaskTrait<'s> {
     fn is_child_running(&self) -> bool;
-    fn kill_child(&mut self);
-    fn try_start_execution(&mut self, ctx: egui::Context) -> Result<ChildApp, ExecutionError>;
+    fn is_child_paused(&self) -> bool;
+    /// Buttons for pausing, signalling and stopping the running child.
+    fn update_child_controls(&mut self, _: &mut Ui);
+    fn try_start_execution(
+        &mut self,
+        params: &RunParams,
+        ctx: egui::Context,
+    ) -> Result<ChildApp, ExecutionError>;
     fn update_stdin(&mut self, _: &mut Ui);
+    fn update_interactive_stdin(&mut self, _: &mut Ui);
 }
 
 /// Main object representing the [`egui`] ui. The lifetime is approximitely static and annotated as `'s`
@@ -330,7 +520,54 @@ struct Klask<'s, PlatformState> {
     /// First string is a description
     working_dir: Option<(String, String)>,
     output: Output,
+    output_view: OutputView,
+    /// Line typed for the running child, `None` unless [`Settings::interactive_stdin`] is set
+    interactive_stdin: Option<String>,
+    /// Size of the output panel in characters, `None` unless [`Settings::pty`] is set
+    terminal: Option<TerminalSize>,
+    /// See [`Settings::output_log_dir`]
+    output_log_dir: Option<std::path::PathBuf>,
+    /// See [`Settings::stop_grace_period`]
+    stop_grace_period: std::time::Duration,
+    /// Whether the next runs have a timeout, and the timeout. See [`Settings::timeout`]
+    /// `None` when runs can't time out.
+    timeout: Option<(bool, std::time::Duration)>,
     app: Command,
+    /// Name of the binary displayed in the command line preview.
+    program: String,
+    /// Syntax of the command line preview.
+    shell: ShellSyntax,
+    /// Pasted command line and the result of the last import
+    import: (String, Option<String>),
+    /// Presets from [`Settings::presets`], can't be deleted
+    builtin_presets: Vec<Preset>,
+    /// Presets saved by the user
+    presets: Vec<Preset>,
+    /// Name of the selected preset, used when saving
+    preset_name: String,
+    /// See [`Settings::remember_session`]
+    remember_session: bool,
+    /// See [`Settings::forget_args`]
+    forget_args: Vec<String>,
+    /// See [`Settings::forget_env`]
+    forget_env: bool,
+    /// See [`Settings::forget_stdin`]
+    forget_stdin: bool,
+    /// Past runs, the latest is last
+    history: Vec<Run>,
+    /// See [`Settings::max_history`]
+    max_history: usize,
+    /// See [`Settings::max_output_lines`]
+    max_output_lines: usize,
+    /// Index of the run in [`Klask::history`] whose output is displayed
+    shown_run: Option<usize>,
+    /// See [`Settings::on_run_finished`]
+    on_run_finished: Option<OnRunFinished>,
+    /// Index in [`Klask::themes`] selected by the user, `None` follows the system theme
+    theme: Option<usize>,
+    themes: Vec<Theme>,
+    /// Theme and system theme the style was last set for
+    applied_theme: Option<(usize, Option<eframe::Theme>)>,
 
     custom_font: Option<Cow<'static, [u8]>>,
     localization: &'s Localization,
@@ -340,7 +577,7 @@ struct Klask<'s, PlatformState> {
     platform_state: PlatformState,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 enum Tab {
     Arguments,
     Env,
@@ -349,11 +586,25 @@ enum Tab {
 
 impl<'s, State> eframe::App for Klask<'s, State>
 where
+    State: PlatformState,
     Self: KlaskTrait<'s>,
 {
-    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        self.apply_theme(ctx, frame.info().system_theme);
+        if let Some(status) = self.child_mut().and_then(ChildApp::update_status) {
+            if let Some(run) = self.history.last_mut() {
+                run.status = Some(status.clone());
+            }
+            if let Some(on_run_finished) = &self.on_run_finished {
+                on_run_finished.call(&status);
+            }
+        }
+        self.update_history(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
+                self.update_presets(ui);
+
                 // Tab selection
                 let tab_count =
                     1 + usize::from(self.env.is_some()) + usize::from(self.stdin.is_some());
@@ -418,6 +669,8 @@ where
                     Tab::Stdin => self.update_stdin(ui),
                 }
 
+                self.update_command_line(ui);
+
                 // Run button row
                 ui.horizontal(|ui| {
                     if ui
@@ -427,56 +680,123 @@ where
                         )
                         .clicked()
                     {
-                        match self.try_start_execution(ctx.clone()) {
-                            Ok(child) => {
-                                // Reset
-                                self.state.update_validation_error("", "");
-                                self.output = Output::new_with_child(child);
-                            }
-                            Err(err) => {
-                                if let ExecutionError::ValidationError { name, message } = &err {
-                                    self.state.update_validation_error(name, message);
-                                }
-                                self.output = Output::Err(err);
-                            }
-                        }
+                        let params = self.run_params();
+                        self.start_run(params, ctx);
                     }
 
-                    if self.is_child_running() && ui.button(&self.localization.kill).clicked() {
-                        self.kill_child();
-                    }
+                    self.update_child_controls(ui);
 
-                    if self.is_child_running() {
+                    if self.is_child_paused() {
+                        ui.label(&self.localization.paused);
+                    } else if self.is_child_running() {
                         let mut running_text = String::from(&self.localization.running);
                         for _ in 0..((2.0 * ui.input(|i| i.time)) as i32 % 4) {
                             running_text.push('.');
                         }
                         ui.label(running_text);
                     }
+                    if let Some(remaining) = self.child().and_then(ChildApp::remaining) {
+                        ui.label(format!(
+                            "{} {}",
+                            self.localization.times_out_in,
+                            format_duration(remaining.as_secs_f64())
+                        ));
+                    }
+
+                    if let Some((enabled, timeout)) = &mut self.timeout {
+                        ui.add_space(10.0);
+                        ui.checkbox(enabled, &self.localization.timeout);
+                        if *enabled {
+                            let mut seconds = timeout.as_secs_f64();
+                            ui.add(
+                                egui::DragValue::new(&mut seconds)
+                                    .clamp_range(1.0..=MAX_TIMEOUT_SECONDS)
+                                    .speed(1.0)
+                                    .suffix("s"),
+                            );
+                            *timeout = std::time::Duration::from_secs_f64(
+                                seconds.clamp(1.0, MAX_TIMEOUT_SECONDS),
+                            );
+                        }
+                    }
+
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        self.update_theme(ui);
+                    });
                 });
 
                 // If on wasm must poll the child to make progress.
                 #[cfg(target_arch = "wasm32")]
                 if let Some(ref mut child) = self.child_mut() {
                     match child.poll() {
-                        Poll::Ready(()) => self.kill_child(),
+                        Poll::Ready(()) => child.kill(),
                         Poll::Pending => (),
                     }
                 };
-                ui.add(&mut self.output);
+                if let Some(terminal) = &mut self.terminal {
+                    let size = terminal_size(ui);
+                    if *terminal != size {
+                        *terminal = size;
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(child) = self.child() {
+                            child.resize(size);
+                        }
+                    }
+                }
+                let status = match self.shown_run.and_then(|i| self.history.get_mut(i)) {
+                    Some(run) if !run.output.is_empty() => {
+                        show_output(
+                            ui,
+                            &mut run.output,
+                            &mut self.output_view,
+                            self.localization,
+                        );
+                        run.status.as_ref()
+                    }
+                    _ => {
+                        self.output
+                            .show(ui, &mut self.output_view, self.localization);
+                        self.child().and_then(ChildApp::status)
+                    }
+                };
+                if let Some(status) = status {
+                    show_status(ui, status, &self.output_view, self.localization);
+                }
+                if self.shown_run.map_or(true, |i| i + 1 == self.history.len()) {
+                    self.update_interactive_stdin(ui);
+                }
             });
         });
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, PRESETS_KEY, &self.presets);
+        if self.remember_session {
+            eframe::set_value(storage, SESSION_KEY, &self.to_preset());
+            eframe::set_value(storage, TAB_KEY, &self.tab);
+            let theme = self.theme.map(|i| &self.themes[i].name);
+            eframe::set_value(storage, THEME_KEY, &theme);
+        }
+    }
+
+    fn persist_native_window(&self) -> bool {
+        self.remember_session
+    }
+
+    fn persist_egui_memory(&self) -> bool {
+        self.remember_session
+    }
 }
 
 impl<'s, State> Klask<'s, State>
 where
+    State: PlatformState,
     Self: KlaskTrait<'s>,
 {
     #[allow(dead_code)]
     fn child(&self) -> Option<&ChildApp> {
         match &self.output {
-            Output::Child(child, _) => Some(child),
+            Output::Child(child, ..) => Some(child),
             _ => None,
         }
     }
@@ -484,7 +804,7 @@ where
     #[allow(dead_code)]
     fn child_mut(&mut self) -> Option<&mut ChildApp> {
         match &mut self.output {
-            Output::Child(child, _) => Some(child),
+            Output::Child(child, ..) => Some(child),
             _ => None,
         }
     }
@@ -492,6 +812,28 @@ where
     fn setup(&mut self, cc: &CreationContext) {
         cc.egui_ctx.set_style(self.style.clone());
 
+        if let Some(storage) = cc.storage {
+            if let Some(presets) = eframe::get_value(storage, PRESETS_KEY) {
+                self.presets = presets;
+            }
+
+            if self.remember_session {
+                if let Some(session) = eframe::get_value(storage, SESSION_KEY) {
+                    self.load_preset(&session);
+                }
+                match eframe::get_value(storage, TAB_KEY) {
+                    Some(Tab::Env) if self.env.is_none() => {}
+                    Some(Tab::Stdin) if self.stdin.is_none() => {}
+                    Some(tab) => self.tab = tab,
+                    None => {}
+                }
+                if let Some(theme) = eframe::get_value::<Option<String>>(storage, THEME_KEY) {
+                    self.theme =
+                        theme.and_then(|name| self.themes.iter().position(|t| t.name == name));
+                }
+            }
+        }
+
         if let Some(custom_font) = self.custom_font.take() {
             let font_name = String::from("custom_font");
             let mut fonts = FontDefinitions::default();
@@ -521,6 +863,403 @@ where
         }
     }
 
+    /// Validated parameters for running the child with the current form.
+    fn run_params(&mut self) -> Result<RunParams, ExecutionError> {
+        let args = self.state.get_cmd_args(vec![])?;
+
+        // Check for validation errors
+        self.app.try_get_matches_from_mut(args.iter())?;
+
+        if self
+            .env
+            .as_ref()
+            .and_then(|(_, v)| v.iter().find(|(key, _)| key.is_empty()))
+            .is_some()
+        {
+            return Err(self
+                .localization
+                .error_env_var_cant_be_empty
+                .as_str()
+                .into());
+        }
+
+        Ok(RunParams {
+            args,
+            env: self.env.clone().map(|(_, env)| env),
+            stdin: self.stdin.clone().map(|(_, stdin)| stdin),
+            working_dir: self.working_dir.clone().map(|(_, dir)| dir),
+        })
+    }
+
+    /// Start the child and add it to the history, or display the error.
+    fn start_run(&mut self, params: Result<RunParams, ExecutionError>, ctx: &Context) {
+        // Keep the output of the previous run in its history entry
+        if let Output::Child(_, output, ..) = std::mem::replace(&mut self.output, Output::None) {
+            if let Some(run) = self.history.last_mut() {
+                run.output = *output;
+            }
+        }
+        self.shown_run = None;
+
+        let result = params.and_then(|params| {
+            let log = match &self.output_log_dir {
+                Some(dir) => Some(OutputLog::create(dir, self.app.get_name())?),
+                None => None,
+            };
+            let mut child = self.try_start_execution(&params, ctx.clone())?;
+            if let Some((true, timeout)) = self.timeout {
+                child.set_timeout(timeout, self.stop_grace_period);
+            }
+            Ok((child, params, log))
+        });
+        match result {
+            Ok((child, params, log)) => {
+                // Reset
+                self.state.update_validation_error(None);
+                self.history.push(Run::new(params));
+                if self.history.len() > self.max_history {
+                    self.history.remove(0);
+                }
+                let rows = self
+                    .terminal
+                    .map_or(terminal::DEFAULT_ROWS, |(_, rows)| rows);
+                self.output = Output::new_with_child(child, rows, self.max_output_lines, log);
+            }
+            Err(err) => {
+                match &err {
+                    ExecutionError::ValidationError(err) => {
+                        self.state.update_validation_error(Some(err))
+                    }
+                    _ => self.state.update_validation_error(None),
+                }
+                self.output = Output::Err(err);
+            }
+        }
+    }
+
+    /// Fill the form with the parameters of a past run.
+    fn restore_run(&mut self, params: &RunParams) {
+        let args: Vec<_> = params.args.iter().map(OsString::from).collect();
+        self.state.set_from_args(&args);
+        self.state.update_validation_error(None);
+        if let (Some((_, env)), Some(run_env)) = (&mut self.env, &params.env) {
+            *env = run_env.clone();
+        }
+        if let (Some((_, stdin)), Some(run_stdin)) = (&mut self.stdin, &params.stdin) {
+            *stdin = run_stdin.clone();
+        }
+        if let (Some((_, working_dir)), Some(run_working_dir)) =
+            (&mut self.working_dir, &params.working_dir)
+        {
+            *working_dir = run_working_dir.clone();
+        }
+    }
+
+    /// Side panel listing past runs, hidden until something is run.
+    fn update_history(&mut self, ctx: &Context) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        let running = self.is_child_running();
+        let mut restore = None;
+        let mut rerun = None;
+
+        egui::SidePanel::right("history").show(ctx, |ui| {
+            ui.heading(&self.localization.history);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (index, run) in self.history.iter().enumerate().rev() {
+                    let params = &run.params;
+                    let env: Vec<_> = params.env.clone().unwrap_or_default();
+                    let command_line = self.shell.command_line(
+                        &self.program,
+                        &params.args,
+                        &env,
+                        params.working_dir.as_deref().filter(|dir| !dir.is_empty()),
+                    );
+
+                    let latest = index + 1 == self.history.len();
+                    let shown = self.shown_run.map_or(latest, |i| i == index);
+                    if ui
+                        .selectable_label(shown, RichText::new(&command_line).monospace())
+                        .clicked()
+                    {
+                        self.shown_run = Some(index);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.weak(format_time(run.started));
+                        match &run.status {
+                            Some(status) => {
+                                let elapsed = format_duration(status.elapsed.as_secs_f64());
+                                let color = if status.exit.success() {
+                                    ui.visuals().text_color()
+                                } else {
+                                    ui.visuals().error_fg_color
+                                };
+                                if status.exit == ExitStatus::TimedOut {
+                                    ui.colored_label(color, &self.localization.timed_out);
+                                }
+                                ui.colored_label(color, elapsed)
+                            }
+                            None => ui.label(&self.localization.running),
+                        };
+                        if ui.small_button(&self.localization.restore).clicked() {
+                            restore = Some(index);
+                        }
+                        if ui
+                            .add_enabled(!running, Button::new(&self.localization.rerun).small())
+                            .clicked()
+                        {
+                            rerun = Some(index);
+                        }
+                    });
+                    ui.separator();
+                }
+            });
+        });
+
+        if let Some(index) = restore {
+            let params = self.history[index].params.clone();
+            self.restore_run(&params);
+        }
+        if let Some(index) = rerun {
+            let params = self.history[index].params.clone();
+            self.start_run(Ok(params), ctx);
+        }
+    }
+
+    /// Sets the style for the selected theme when it or the system theme changed.
+    fn apply_theme(&mut self, ctx: &Context, system: Option<eframe::Theme>) {
+        let index = self.theme.unwrap_or_else(|| {
+            let dark = system.map(|theme| theme == eframe::Theme::Dark);
+            self.themes
+                .iter()
+                .position(|theme| Some(theme.visuals.dark_mode) == dark)
+                .unwrap_or(0)
+        });
+        if self.applied_theme == Some((index, system)) {
+            return;
+        }
+        self.applied_theme = Some((index, system));
+
+        let Some(theme) = self.themes.get(index) else {
+            return;
+        };
+        let mut style = self.style.clone();
+        style.visuals = theme.visuals.clone();
+        style.visuals.error_fg_color = theme.error_color;
+        ctx.set_style(style);
+        self.output_view.stderr_color = theme.stderr_color;
+        self.output_view.success_color = theme.success_color;
+        self.output_view.palette = theme.palette.clone();
+    }
+
+    fn update_theme(&mut self, ui: &mut Ui) {
+        if self.themes.is_empty() {
+            return;
+        }
+        let selected = match self.theme {
+            Some(i) => &self.themes[i].name,
+            None => &self.localization.system_theme,
+        };
+        ComboBox::from_id_source("theme")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.theme, None, &self.localization.system_theme);
+                for (i, theme) in self.themes.iter().enumerate() {
+                    ui.selectable_value(&mut self.theme, Some(i), &theme.name);
+                }
+            });
+    }
+
+    /// Collapsible row for saving and loading presets.
+    fn update_presets(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new(&self.localization.presets).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ComboBox::from_id_source("presets")
+                    .selected_text(&self.preset_name)
+                    .show_ui(ui, |ui| {
+                        for preset in self.builtin_presets.iter().chain(&self.presets) {
+                            ui.selectable_value(
+                                &mut self.preset_name,
+                                preset.name.clone(),
+                                &preset.name,
+                            );
+                        }
+                    });
+                ui.add(
+                    TextEdit::singleline(&mut self.preset_name)
+                        .hint_text(&self.localization.preset_name)
+                        .desired_width(150.0),
+                );
+
+                let builtin = self
+                    .builtin_presets
+                    .iter()
+                    .position(|p| p.name == self.preset_name);
+                let saved = self.presets.iter().position(|p| p.name == self.preset_name);
+
+                if ui
+                    .add_enabled(
+                        builtin.is_some() || saved.is_some(),
+                        Button::new(&self.localization.load),
+                    )
+                    .clicked()
+                {
+                    let preset = match (builtin, saved) {
+                        (Some(i), _) => self.builtin_presets[i].clone(),
+                        (_, Some(i)) => self.presets[i].clone(),
+                        _ => unreachable!(),
+                    };
+                    self.load_preset(&preset);
+                }
+                if ui
+                    .add_enabled(
+                        builtin.is_none() && !self.preset_name.is_empty(),
+                        Button::new(&self.localization.save),
+                    )
+                    .clicked()
+                {
+                    let preset = self.to_preset();
+                    match saved {
+                        Some(i) => self.presets[i] = preset,
+                        None => self.presets.push(preset),
+                    }
+                }
+                if ui
+                    .add_enabled(saved.is_some(), Button::new(&self.localization.delete))
+                    .clicked()
+                {
+                    self.presets.remove(saved.unwrap());
+                }
+            });
+        });
+    }
+
+    /// The whole form as a preset named [`Klask::preset_name`], without [`Klask::forget_args`]
+    /// and the env and stdin if they are forgotten too.
+    fn to_preset(&self) -> Preset {
+        let mut preset = Preset::new(self.preset_name.clone());
+        self.state.save_preset(&mut preset);
+        preset
+            .args
+            .retain(|path, _| !self.forget_args.contains(path));
+        if let Some((_, env)) = self.env.as_ref().filter(|_| !self.forget_env) {
+            preset.env = env.clone();
+        }
+        if let Some((_, stdin)) = self.stdin.as_ref().filter(|_| !self.forget_stdin) {
+            preset.stdin = Some(stdin.into());
+        }
+        if let Some((_, working_dir)) = &self.working_dir {
+            preset.working_dir = working_dir.clone();
+        }
+        preset
+    }
+
+    fn load_preset(&mut self, preset: &Preset) {
+        self.state.load_preset(preset);
+        self.state.update_validation_error(None);
+        if let Some((_, env)) = &mut self.env {
+            *env = preset.env.clone();
+        }
+        if let Some((_, stdin)) = &mut self.stdin {
+            *stdin = preset
+                .stdin
+                .as_ref()
+                .map_or_else(|| StdinType::Text(String::new()), Into::into);
+        }
+        if let Some((_, working_dir)) = &mut self.working_dir {
+            *working_dir = preset.working_dir.clone();
+        }
+    }
+
+    /// Collapsible preview of the command line that will be run.
+    fn update_command_line(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new(&self.localization.command).show(ui, |ui| {
+            let args = match self.state.get_partial_cmd_args(vec![]) {
+                Ok(args) => args,
+                Err(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                    return;
+                }
+            };
+            let env: Vec<_> = self
+                .env
+                .as_ref()
+                .map(|(_, env)| env.iter().filter(|(k, _)| !k.is_empty()).cloned().collect())
+                .unwrap_or_default();
+            let working_dir = self
+                .working_dir
+                .as_ref()
+                .map(|(_, dir)| dir.as_str())
+                .filter(|dir| !dir.is_empty());
+            let command_line = self
+                .shell
+                .command_line(&self.program, &args, &env, working_dir);
+
+            ui.horizontal(|ui| {
+                for shell in ShellSyntax::ALL {
+                    ui.selectable_value(&mut self.shell, shell, shell.name());
+                }
+                ui.add_space(20.0);
+                if ui.button(&self.localization.copy).clicked() {
+                    ui.output_mut(|o| o.copied_text = command_line.clone());
+                }
+            });
+            ui.label(RichText::new(command_line).monospace());
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.import.0)
+                        .hint_text(&self.localization.paste_command_line)
+                        .font(egui::TextStyle::Monospace),
+                );
+                if ui.button(&self.localization.import_command).clicked() {
+                    self.import.1 = self.import_command_line();
+                }
+            });
+            if let Some(report) = &self.import.1 {
+                ui.colored_label(ui.visuals().error_fg_color, report);
+            }
+        });
+    }
+
+    /// Fill the form from the pasted command line, returns an error or a list of skipped arguments
+    fn import_command_line(&mut self) -> Option<String> {
+        let Some(mut args) = self.shell.split(&self.import.0) else {
+            return Some(self.localization.error_unclosed_quote.clone());
+        };
+
+        // Drop the binary name if it was pasted too
+        let stem = |s: &str| {
+            std::path::Path::new(s)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        };
+        let names = [stem(&self.program), Some(self.app.get_name().to_string())];
+        if args
+            .first()
+            .is_some_and(|first| names.contains(&stem(first)))
+        {
+            args.remove(0);
+        }
+
+        let args: Vec<_> = args.into_iter().map(OsString::from).collect();
+        let unplaced = self.state.set_from_args(&args);
+        self.state.update_validation_error(None);
+
+        (!unplaced.is_empty()).then(|| {
+            let unplaced: Vec<_> = unplaced.iter().map(|arg| arg.to_string_lossy()).collect();
+            format!(
+                "{}{}",
+                self.localization.unplaced_arguments,
+                unplaced.join(" ")
+            )
+        })
+    }
+
     fn update_env(&mut self, ui: &mut Ui) {
         let (ref desc, env) = self.env.as_mut().unwrap();
 
@@ -592,29 +1331,27 @@ where
         }
     }
 
-    fn kill_child(&mut self) {
-        // No child process to kill. Just tell the child to die.
-        self.child_mut().map(|child| child.kill());
+    fn is_child_paused(&self) -> bool {
+        false
     }
 
-    fn try_start_execution(&mut self, ctx: egui::Context) -> Result<ChildApp, ExecutionError> {
-        let args = self.state.get_cmd_args(vec![])?;
-
-        // Check for validation errors and get matches for arguments.
-        let matches = self.app.try_get_matches_from_mut(args.iter())?;
-
-        if self
-            .env
-            .as_ref()
-            .and_then(|(_, v)| v.iter().find(|(key, _)| key.is_empty()))
-            .is_some()
-        {
-            return Err(self
-                .localization
-                .error_env_var_cant_be_empty
-                .as_str()
-                .into());
+    fn update_child_controls(&mut self, ui: &mut Ui) {
+        let localization = self.localization;
+        if let Some(child) = self.child_mut() {
+            // No child process to kill. Just tell the child to die.
+            if child.is_running() && ui.button(&localization.kill).clicked() {
+                child.kill();
+            }
         }
+    }
+
+    fn try_start_execution(
+        &mut self,
+        params: &RunParams,
+        ctx: egui::Context,
+    ) -> Result<ChildApp, ExecutionError> {
+        // Get matches for arguments.
+        let matches = self.app.try_get_matches_from_mut(params.args.iter())?;
 
         Ok(ChildApp::new(
             ctx,
@@ -626,51 +1363,147 @@ where
     fn update_stdin(&mut self, _: &mut Ui) {
         unimplemented!("This is invalid for wasm klask. No stdin in wasm to direct to.")
     }
+
+    fn update_interactive_stdin(&mut self, _: &mut Ui) {
+        // No stdin in wasm
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl<'s> KlaskTrait<'s> for Klask<'s, Native> {
     fn is_child_running(&self) -> bool {
         match &self.output {
-            Output::Child(child, _) => child.is_running(),
+            Output::Child(child, ..) => child.is_running(),
             _ => false,
         }
     }
 
-    fn kill_child(&mut self) {
-        if let Output::Child(child, _) = &mut self.output {
-            child.kill();
+    fn is_child_paused(&self) -> bool {
+        match &self.output {
+            Output::Child(child, ..) => child.is_paused(),
+            _ => false,
         }
     }
 
-    fn try_start_execution(&mut self, ctx: egui::Context) -> Result<ChildApp, ExecutionError> {
-        let args = self.state.get_cmd_args(vec![])?;
-
-        // Check for validation errors
-        self.app.try_get_matches_from_mut(args.iter())?;
+    fn update_child_controls(&mut self, ui: &mut Ui) {
+        let Output::Child(child, ..) = &mut self.output else {
+            return;
+        };
+        if !child.is_running() || !child.can_stop() {
+            return;
+        }
 
-        if self
-            .env
-            .as_ref()
-            .and_then(|(_, v)| v.iter().find(|(key, _)| key.is_empty()))
-            .is_some()
+        let localization = self.localization;
+        #[cfg(unix)]
         {
-            return Err(self
-                .localization
-                .error_env_var_cant_be_empty
-                .as_str()
-                .into());
+            if child.is_paused() {
+                if ui.button(&localization.resume).clicked() {
+                    child.resume();
+                }
+            } else if ui.button(&localization.pause).clicked() {
+                child.pause();
+            }
+            ui.menu_button(&localization.send_signal, |ui| {
+                for (name, signal) in child_app::SIGNALS {
+                    if ui.button(name).clicked() {
+                        child.signal(signal);
+                        ui.close_menu();
+                    }
+                }
+            });
+
+            if ui.button(&localization.interrupt).clicked() {
+                child.stop(Stop::Interrupt);
+            }
+            if ui.button(&localization.terminate).clicked() {
+                child.stop(Stop::Terminate(self.stop_grace_period));
+            }
+            if ui.button(&localization.force_kill).clicked() {
+                child.stop(Stop::Kill);
+            }
+        }
+        #[cfg(not(unix))]
+        if ui.button(&localization.kill).clicked() {
+            child.stop(Stop::Kill);
         }
+    }
 
+    fn try_start_execution(
+        &mut self,
+        params: &RunParams,
+        ctx: egui::Context,
+    ) -> Result<ChildApp, ExecutionError> {
+        let params = params.clone();
+        #[cfg(unix)]
+        if let Some(app) = &self.platform_state.in_process {
+            let matches = self.app.try_get_matches_from_mut(params.args.iter())?;
+            return ChildApp::run_in_process(
+                app.clone(),
+                matches,
+                params.stdin,
+                self.interactive_stdin.is_some(),
+                ctx,
+            );
+        }
         ChildApp::run(
-            args,
-            self.env.clone().map(|(_, env)| env),
-            self.stdin.clone().map(|(_, stdin)| stdin),
-            self.working_dir.clone().map(|(_, dir)| dir),
+            params.args,
+            params.env,
+            params.stdin,
+            params.working_dir,
+            self.interactive_stdin.is_some(),
+            self.terminal,
             ctx,
         )
     }
 
+    fn update_interactive_stdin(&mut self, ui: &mut Ui) {
+        let Output::Child(child, ..) = &mut self.output else {
+            return;
+        };
+
+        if let Some((sent, total)) = child.stdin_progress() {
+            ui.add(
+                egui::ProgressBar::new(sent as f32 / total as f32).text(format!(
+                    "{} {} / {}",
+                    self.localization.sending_input,
+                    format_bytes(sent),
+                    format_bytes(total)
+                )),
+            );
+        }
+        if let Some(err) = child.stdin_error() {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("{} {err}", self.localization.input_error),
+            );
+        }
+
+        let Some(line) = &mut self.interactive_stdin else {
+            return;
+        };
+        if !child.is_running() || !child.has_stdin() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                TextEdit::singleline(line)
+                    .hint_text(&self.localization.input_line)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(ui.available_width() / 2.0),
+            );
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if entered || ui.button(&self.localization.send).clicked() {
+                child.write_stdin(&format!("{line}\n"));
+                line.clear();
+                response.request_focus();
+            }
+            if ui.button(&self.localization.close_stdin).clicked() {
+                child.close_stdin();
+            }
+        });
+    }
+
     fn update_stdin(&mut self, ui: &mut Ui) {
         let (ref desc, stdin) = self.stdin.as_mut().unwrap();
 
@@ -714,15 +1547,33 @@ impl<'s> KlaskTrait<'s> for Klask<'s, Native> {
     }
 }
 
+/// Columns that fit in the width left in `ui` and rows that fit in the window.
+fn terminal_size(ui: &Ui) -> TerminalSize {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let (width, height) = ui.fonts(|f| (f.glyph_width(&font, '0'), f.row_height(&font)));
+    let columns = (ui.available_width() / width).clamp(20.0, 1000.0);
+    let rows = (output::output_height(ui) / height).clamp(5.0, 500.0);
+    (columns as u16, rows as u16)
+}
+
+/// Initial state of [`Klask::timeout`], a minute when there's no default timeout.
+fn timeout_setting(timeout: Option<std::time::Duration>) -> (bool, std::time::Duration) {
+    (
+        timeout.is_some(),
+        timeout.unwrap_or(std::time::Duration::from_secs(60)),
+    )
+}
+
 fn set_error_style(ui: &mut Ui) {
     let style = ui.style_mut();
-    style.visuals.widgets.inactive.bg_stroke.color = Color32::RED;
+    let color = style.visuals.error_fg_color;
+    style.visuals.widgets.inactive.bg_stroke.color = color;
     style.visuals.widgets.inactive.bg_stroke.width = 1.0;
-    style.visuals.widgets.hovered.bg_stroke.color = Color32::RED;
-    style.visuals.widgets.active.bg_stroke.color = Color32::RED;
-    style.visuals.widgets.open.bg_stroke.color = Color32::RED;
-    style.visuals.widgets.noninteractive.bg_stroke.color = Color32::RED;
-    style.visuals.selection.stroke.color = Color32::RED;
+    style.visuals.widgets.hovered.bg_stroke.color = color;
+    style.visuals.widgets.active.bg_stroke.color = color;
+    style.visuals.widgets.open.bg_stroke.color = color;
+    style.visuals.widgets.noninteractive.bg_stroke.color = color;
+    style.visuals.selection.stroke.color = color;
 }
 
 fn append_on_new_word(mut result: String, first_word: bool, character: char) -> String {
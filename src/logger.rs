@@ -81,10 +81,10 @@ impl log::Log for Logger {
             }),
             log::Level::Info => Box::new(move |ui: &mut Ui| ui.label(msg)),
             log::Level::Warn => {
-                Box::new(move |ui: &mut Ui| ui.colored_label(Color32::YELLOW, msg))
+                Box::new(move |ui: &mut Ui| ui.colored_label(ui.visuals().warn_fg_color, msg))
             }
             log::Level::Error => {
-                Box::new(move |ui: &mut Ui| ui.colored_label(Color32::RED, msg))
+                Box::new(move |ui: &mut Ui| ui.colored_label(ui.visuals().error_fg_color, msg))
             }
         }) */
     }
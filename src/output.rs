@@ -1,11 +1,24 @@
+use crate::buffer::{LayoutKey, LineLayout, OutputBuffer};
 use crate::child_app::ChildApp;
 use crate::error::ExecutionError;
-use cansi::{v3::CategorisedSlice, Color, Intensity};
-use eframe::egui::{vec2, Color32, Label, ProgressBar, RichText, Ui, Widget};
+use crate::export::{self, ExportFormat, OutputLog, PageColors};
+use crate::history::{format_bytes, format_duration};
+use crate::search::Search;
+use crate::settings::{Localization, Palette, Settings};
+use crate::terminal::{self, Color, Style, Terminal};
+use eframe::egui::{
+    output::OpenUrl, text::CCursor, text::LayoutJob, vec2, Color32, CursorIcon, FontId,
+    ProgressBar, Rect, Response, RichText, ScrollArea, Sense, Stroke, TextFormat, TextStyle, Ui,
+};
 use linkify::{LinkFinder, LinkKind};
+use regex::Regex;
 use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use std::io::Write;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Displays a progress bar in the output. First call creates
 /// a progress bar and future calls update it.
@@ -55,99 +68,521 @@ pub fn progress_bar_with_id(id: impl Hash, description: &str, value: f32) {
     OutputType::ProgressBar(description.to_string(), value).send(h.finish());
 }
 
+/// How a run of the app ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Exited on its own with an exit code. Finished futures on wasm have code 0.
+    Code(i32),
+    /// Terminated by a signal that wasn't sent by the terminate or kill buttons.
+    Signal(i32),
+    /// Stopped with the terminate or kill buttons.
+    Killed,
+    /// Terminated after running for longer than the timeout.
+    TimedOut,
+}
+
+impl ExitStatus {
+    /// Whether the app exited with code 0.
+    pub fn success(&self) -> bool {
+        *self == ExitStatus::Code(0)
+    }
+}
+
+/// Status of a finished run.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RunStatus {
+    /// How the run ended.
+    pub exit: ExitStatus,
+    /// Time from starting the run until klask noticed it ended.
+    pub elapsed: Duration,
+    /// Most resident memory used by the app and the processes it started. Only on Linux.
+    pub peak_memory: Option<u64>,
+    /// CPU time used by the app and the processes it started. Only on Linux.
+    pub cpu_time: Option<Duration>,
+}
+
+/// Called with the status of every finished run. See [`crate::Settings::on_run_finished`].
+/// ```no_run
+/// # use klask::{output::OnRunFinished, Settings};
+/// let mut settings = Settings::default();
+/// settings.on_run_finished = Some(OnRunFinished::new(|status| {
+///     eprintln!("Run ended with {:?} after {:?}", status.exit, status.elapsed)
+/// }));
+/// ```
+#[derive(Clone)]
+pub struct OnRunFinished(Arc<dyn Fn(&RunStatus)>);
+
+impl OnRunFinished {
+    /// Wraps the callback.
+    pub fn new(f: impl Fn(&RunStatus) + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn call(&self, status: &RunStatus) {
+        (self.0)(status)
+    }
+}
+
+impl Debug for OnRunFinished {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnRunFinished").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for OnRunFinished {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Banner under the output saying how the run ended.
+pub(crate) fn show_status(
+    ui: &mut Ui,
+    status: &RunStatus,
+    view: &OutputView,
+    localization: &Localization,
+) {
+    let text = match status.exit {
+        ExitStatus::Code(0) => localization.exited_successfully.clone(),
+        ExitStatus::Code(code) => format!("{} {code}", localization.exited_with_code),
+        ExitStatus::Signal(signal) => format!("{} {signal}", localization.exited_with_signal),
+        ExitStatus::Killed => localization.killed.clone(),
+        ExitStatus::TimedOut => localization.timed_out.clone(),
+    };
+    let color = if status.exit.success() {
+        view.success_color
+    } else {
+        ui.visuals().error_fg_color
+    };
+    let mut summary = format_duration(status.elapsed.as_secs_f64());
+    if let Some(cpu_time) = status.cpu_time {
+        let cpu_time = format_duration(cpu_time.as_secs_f64());
+        summary += &format!(", {} {cpu_time}", localization.cpu_time);
+    }
+    if let Some(peak_memory) = status.peak_memory {
+        let peak_memory = format_bytes(peak_memory);
+        summary += &format!(", {} {peak_memory}", localization.peak_memory);
+    }
+    ui.label(
+        RichText::new(format!("{text} ({summary})"))
+            .color(color)
+            .strong(),
+    );
+}
+
 #[derive(Debug)]
 pub(crate) enum Output {
     None,
     Err(ExecutionError),
-    Child(ChildApp, Vec<(u64, OutputType)>),
+    Child(
+        Box<ChildApp>,
+        Box<OutputBuffer>,
+        Box<Terminal>,
+        Option<OutputLog>,
+    ),
 }
 
 impl Output {
-    pub fn new_with_child(child: ChildApp) -> Self {
-        Self::Child(child, vec![])
+    /// `rows` is the height of the screen for output that moves the cursor,
+    /// `max_lines` is the limit of the lines kept. Output is also written to `log`.
+    pub fn new_with_child(
+        child: ChildApp,
+        rows: u16,
+        max_lines: usize,
+        log: Option<OutputLog>,
+    ) -> Self {
+        Self::Child(
+            Box::new(child),
+            Box::new(OutputBuffer::new(max_lines)),
+            Box::new(Terminal::new(rows)),
+            log,
+        )
     }
 }
 
-impl Widget for &mut Output {
-    fn ui(self, ui: &mut Ui) -> eframe::egui::Response {
+impl Output {
+    /// Reads new output from the child and displays it.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        view: &mut OutputView,
+        localization: &Localization,
+    ) -> Response {
         match self {
             Output::None => ui.vertical(|_| {}).response,
-            Output::Err(err) => ui.colored_label(Color32::RED, err.to_string()),
-            Output::Child(child, output) => {
+            Output::Err(err) => ui.colored_label(ui.visuals().error_fg_color, err.to_string()),
+            Output::Child(child, output, terminal, log) => {
                 // Update
-                let str = child.read();
-                let mut iter = str.split(MAGIC);
-
-                if let Some(text) = iter.next() {
-                    if !text.is_empty() {
-                        output.push((0, OutputType::Text(text.to_string())));
-                    }
-                }
-
-                while let Some(id) = iter.next() {
-                    if let Ok(id) = id.parse() {
-                        if let Some(new) = OutputType::parse(&mut iter) {
-                            if let Some((_, exists)) = output.iter_mut().find(|(i, _)| *i == id) {
-                                *exists = new;
-                            } else {
-                                output.push((id, new));
+                for (stream, time, text) in child.read() {
+                    match stream {
+                        Stream::Stdout => parse_stdout(output, terminal, log, &text, time),
+                        Stream::Stderr => {
+                            if let Some(log) = log {
+                                log.write(&text);
                             }
+                            terminal.write(output, stream, &text, time)
                         }
                     }
+                }
+                terminal.flush(output);
+                if let Some(log) = log {
+                    log.flush();
+                }
 
-                    if let Some(text) = iter.next() {
-                        // Get rid of the newline
-                        let text = &text[1..];
-                        if !text.is_empty() {
-                            output.push((0, OutputType::Text(text.to_string())));
-                        }
-                    }
+                #[cfg(target_os = "linux")]
+                if let Some(resources) = child.resources().filter(|_| child.is_running()) {
+                    resources.show(ui, child.elapsed(), localization);
+                }
+
+                show_output(ui, output, view, localization)
+            }
+        }
+    }
+}
+
+/// Splits progress bar messages from the text printed by the child. The text is also written to `log`.
+fn parse_stdout(
+    output: &mut OutputBuffer,
+    terminal: &mut Terminal,
+    log: &mut Option<OutputLog>,
+    str: &str,
+    time: Duration,
+) {
+    let mut iter = str.split(MAGIC);
+    let mut write = |output: &mut OutputBuffer, text: &str| {
+        if let Some(log) = log {
+            log.write(text);
+        }
+        terminal.write(output, Stream::Stdout, text, time);
+    };
+
+    if let Some(t) = iter.next() {
+        write(output, t);
+    }
+
+    while let Some(id) = iter.next() {
+        if let Ok(id) = id.parse() {
+            if let Some(new) = OutputType::parse(&mut iter) {
+                output.set_progress_bar(id, new);
+            }
+        }
+
+        if let Some(t) = iter.next() {
+            // Get rid of the newline
+            write(output, &t[1..]);
+        }
+    }
+}
+
+/// Pipe of the child that a line of output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// How captured output is displayed. Colors are set by the settings or the theme.
+#[derive(Debug, Clone)]
+pub(crate) struct OutputView {
+    /// Only show lines from this stream, `None` shows both interleaved.
+    pub stream: Option<Stream>,
+    /// Color of stderr text that doesn't set its own color.
+    pub stderr_color: Color32,
+    /// Color of the status of successful runs.
+    pub success_color: Color32,
+    pub palette: Palette,
+    pub search: Search,
+    /// Error of the last save of the output.
+    pub save_error: Option<String>,
+}
+
+impl OutputView {
+    pub fn new(palette: Palette, stderr_color: Color32, success_color: Color32) -> Self {
+        Self {
+            stream: None,
+            stderr_color,
+            success_color,
+            palette,
+            search: Search::default(),
+            save_error: None,
+        }
+    }
+}
+
+impl Default for OutputView {
+    fn default() -> Self {
+        let settings = Settings::default();
+        Self::new(
+            settings.palette,
+            settings.stderr_color,
+            settings.success_color,
+        )
+    }
+}
+
+/// Displays captured output with buttons to copy and save it.
+pub(crate) fn show_output(
+    ui: &mut Ui,
+    output: &mut OutputBuffer,
+    view: &mut OutputView,
+    localization: &Localization,
+) -> Response {
+    // Changes in this frame are shown in the next one
+    let stream = view.stream;
+    let regex = view.search.regex();
+    let searching = match &regex {
+        Some(Ok(regex)) => {
+            output.search(regex);
+            true
+        }
+        _ => false,
+    };
+    let only_matching = searching && view.search.only_matching;
+    let shown_len = if only_matching {
+        output.matches(stream).lines.len()
+    } else {
+        output.shown_len(stream)
+    };
+    let shown_index = |output: &OutputBuffer, row: usize| {
+        if only_matching {
+            output.matches(stream).lines[row]
+        } else {
+            output.shown_index(stream, row)
+        }
+    };
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            let shown_lines = || -> Vec<_> {
+                if only_matching {
+                    let matches = output.matches(stream);
+                    matches
+                        .lines
+                        .iter()
+                        .filter_map(|&i| output.get(i))
+                        .collect()
+                } else {
+                    output.shown(stream).collect()
                 }
+            };
+            if ui.button("Copy output").clicked() {
+                let text = shown_lines()
+                    .into_iter()
+                    .map(|o| match o {
+                        OutputType::Text(text, ..) => text,
+                        OutputType::ProgressBar(text, _) => text,
+                    })
+                    .flat_map(|text| terminal::spans(text))
+                    .map(|(_, text)| text)
+                    .collect::<String>();
+                ui.ctx().output_mut(|p| p.copied_text = text);
+            }
 
-                // View
-                ui.vertical(|ui| {
-                    if ui.button("Copy output").clicked() {
-                        ui.ctx().output_mut(|p| {
-                            p.copied_text = output
-                                .iter()
-                                .map(|(_, o)| match o {
-                                    OutputType::Text(text) => text,
-                                    OutputType::ProgressBar(text, _) => text,
-                                })
-                                .flat_map(|text| cansi::v3::categorise_text(text))
-                                .map(|slice| slice.text)
-                                .collect::<String>()
-                        });
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.menu_button(&localization.save_output, |ui| {
+                let names = [
+                    &localization.plain_text,
+                    &localization.ansi_text,
+                    &localization.html,
+                ];
+                for (format, name) in ExportFormat::ALL.into_iter().zip(names) {
+                    if ui.button(name).clicked() {
+                        ui.close_menu();
+                        let visuals = ui.visuals();
+                        let colors = PageColors {
+                            text: visuals.text_color(),
+                            background: visuals.panel_fill,
+                            link: visuals.hyperlink_color,
+                        };
+                        let text = || export::export(shown_lines(), format, view, colors);
+                        view.save_error = export::save(format, text).err().map(|e| e.to_string());
                     }
+                }
+            });
+            if let Some(err) = &view.save_error {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
 
-                    for (_, o) in output {
-                        match o {
-                            OutputType::Text(ref text) => format_output(ui, text),
-                            OutputType::ProgressBar(ref mess, value) => {
-                                // Get rid of the ending newline
-                                ui.add(
-                                    ProgressBar::new(*value)
-                                        .text(&mess[..mess.len() - 1])
-                                        .animate(true),
-                                );
-                            }
-                        }
+            ui.add_space(20.0);
+            let mut selected = stream;
+            ui.selectable_value(&mut selected, None, &localization.all_output);
+            ui.selectable_value(&mut selected, Some(Stream::Stdout), &localization.stdout);
+            ui.selectable_value(&mut selected, Some(Stream::Stderr), &localization.stderr);
+            if selected != stream {
+                view.stream = selected;
+                view.search.current = 0;
+            }
+        });
+
+        let counted = regex.as_ref().map(|regex| match regex {
+            Ok(_) => Ok(output.matches(stream).found.len()),
+            Err(err) => Err(err.clone()),
+        });
+        view.search.show(ui, counted, localization);
+        let current = searching
+            .then(|| output.matches(stream))
+            .and_then(|matches| {
+                let found = &matches.found;
+                view.search.current = view.search.current.min(found.len().saturating_sub(1));
+                found.get(view.search.current).cloned()
+            });
+
+        if output.dropped() > 0 {
+            ui.weak(format!(
+                "{}{}",
+                localization.dropped_lines,
+                output.dropped()
+            ));
+        }
+
+        let key = LayoutKey {
+            palette: view.palette.clone(),
+            stderr_color: view.stderr_color,
+            text_color: ui.visuals().text_color(),
+            pixels_per_point: ui.ctx().pixels_per_point(),
+        };
+        let frame = ui.ctx().frame_nr();
+        output.start_frame(key, frame);
+
+        let row_height = row_height(ui);
+        let height = output_height(ui);
+        ui.spacing_mut().item_spacing.y = 0.0;
+        let scroll_to = match (std::mem::take(&mut view.search.scroll), only_matching) {
+            (false, _) => None,
+            (true, true) => current
+                .as_ref()
+                .and_then(|(index, _)| output.matches(stream).lines.binary_search(index).ok()),
+            (true, false) => current
+                .as_ref()
+                .and_then(|(index, _)| output.shown_row(stream, *index)),
+        };
+        // Sticking to the bottom would override the offset
+        let mut scroll_area = ScrollArea::both()
+            .id_source("output")
+            .auto_shrink([false, true])
+            .max_height(height)
+            .stick_to_bottom(scroll_to.is_none());
+        if let Some(row) = scroll_to {
+            // Put the match in the middle
+            let offset = row as f32 * row_height - (height - row_height) / 2.0;
+            scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+        }
+
+        // Only the visible lines are laid out
+        scroll_area.show_rows(ui, row_height, shown_len, |ui, rows| {
+            for row in rows {
+                let index = shown_index(output, row);
+                let highlight = match &regex {
+                    Some(Ok(regex)) => {
+                        let selected = current.as_ref().filter(|(i, _)| *i == index);
+                        Some((regex, selected.map(|(_, range)| range.clone())))
                     }
-                })
-                .response
+                    _ => None,
+                };
+                show_line(ui, output, index, frame, view, highlight);
+            }
+        });
+    })
+    .response
+}
+
+/// Shows the line at `index`, `highlight` marks the matches of a search and the byte range
+/// of the selected one if it's in this line.
+fn show_line(
+    ui: &mut Ui,
+    output: &mut OutputBuffer,
+    index: usize,
+    frame: u64,
+    view: &OutputView,
+    highlight: Option<(&Regex, Option<Range<usize>>)>,
+) {
+    let (stream, time) = match output.get(index) {
+        Some(OutputType::Text(_, stream, time)) => (*stream, *time),
+        Some(OutputType::ProgressBar(mess, value)) => {
+            // Get rid of the ending newline
+            let response = ui.add(
+                ProgressBar::new(*value)
+                    .text(&mess[..mess.len() - 1])
+                    .animate(true),
+            );
+            if let Some((_, Some(_))) = highlight {
+                let stroke = ui.visuals().selection.stroke;
+                ui.painter().rect_stroke(response.rect, 2.0, stroke);
+            }
+            return;
+        }
+        None => return,
+    };
+
+    let color = (stream == Stream::Stderr).then_some(view.stderr_color);
+    let Some(layout) = output.layout(index, frame, |line| match line {
+        OutputType::Text(text, ..) => layout_line(ui, text, color, &view.palette),
+        OutputType::ProgressBar(..) => unreachable!(),
+    }) else {
+        return;
+    };
+
+    let size = vec2(layout.galley.size().x, row_height(ui));
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    let pos = rect.left_center() - vec2(0.0, layout.galley.size().y / 2.0);
+
+    if let Some(pointer) = response.hover_pos() {
+        let char = layout.galley.cursor_from_pos(pointer - pos).ccursor.index;
+        if let Some((_, url)) = layout.links.iter().find(|(range, _)| range.contains(&char)) {
+            ui.ctx().set_cursor_icon(CursorIcon::PointingHand);
+            if response.clicked() {
+                ui.ctx()
+                    .output_mut(|o| o.open_url = Some(OpenUrl::new_tab(url)));
             }
         }
     }
+    if let Some((regex, selected)) = highlight {
+        let text = &layout.galley.job.text;
+        for found in regex.find_iter(text).filter(|found| !found.is_empty()) {
+            let mut color = ui.visuals().selection.bg_fill;
+            if selected.as_ref() != Some(&found.range()) {
+                color = color.gamma_multiply(0.4);
+            }
+            let start = text[..found.start()].chars().count();
+            let end = start + found.as_str().chars().count();
+            let x = |char| {
+                pos.x
+                    + layout
+                        .galley
+                        .pos_from_cursor(&layout.galley.from_ccursor(CCursor::new(char)))
+                        .min
+                        .x
+            };
+            let rect = Rect::from_x_y_ranges(x(start)..=x(end), rect.y_range());
+            ui.painter().rect_filled(rect, 2.0, color);
+        }
+    }
+    ui.painter().galley(pos, layout.galley);
+    response.on_hover_text(format!("+{}", format_duration(time.as_secs_f64())));
+}
+
+/// Height of every row in the output, text is monospace and progress bars are as high as buttons.
+fn row_height(ui: &Ui) -> f32 {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    ui.fonts(|f| f.row_height(&font_id))
+        .max(ui.spacing().interact_size.y)
+}
+
+/// Maximum height of the scrolling output.
+pub(crate) fn output_height(ui: &Ui) -> f32 {
+    ui.ctx().screen_rect().height() * 0.7
 }
 
 #[derive(Debug)]
 pub(crate) enum OutputType {
-    Text(String),
+    /// Text with the stream it came from and when it arrived, relative to the start of the run.
+    Text(String, Stream, Duration),
     ProgressBar(String, f32),
 }
 
 /// Unicode non-character. Used for sending messages between GUI and user's program
-const MAGIC: char = '\u{5FFFE}';
+pub(crate) const MAGIC: char = '\u{5FFFE}';
 
 fn send_message(data: &[&str]) {
     let stdout = std::io::stdout();
@@ -164,7 +599,7 @@ impl OutputType {
     pub fn send(self, id: u64) {
         // Make sure to get rid of any newlines
         match self {
-            Self::Text(s) => print!("{s}"),
+            Self::Text(s, ..) => print!("{s}"),
             Self::ProgressBar(desc, value) => send_message(&[
                 &id.to_string(),
                 Self::PROGRESS_BAR_STR,
@@ -186,87 +621,116 @@ impl OutputType {
     }
 }
 
-fn format_output(ui: &mut Ui, text: &str) {
-    let output = cansi::v3::categorise_text(text);
-
-    let previous = ui.style().spacing.item_spacing;
-    ui.style_mut().spacing.item_spacing = vec2(0.0, 0.0);
-
-    ui.horizontal_wrapped(|ui| {
-        for CategorisedSlice {
-            text,
-            fg,
-            bg,
-            intensity,
-            italic,
-            underline,
-            strikethrough,
-            ..
-        } in output
-        {
-            for span in LinkFinder::new().spans(text) {
-                match span.kind() {
-                    Some(LinkKind::Url) => ui.hyperlink(span.as_str()),
-                    Some(LinkKind::Email) => {
-                        ui.hyperlink_to(span.as_str(), format!("mailto:{}", span.as_str()))
-                    }
-                    Some(_) | None => {
-                        let mut text = RichText::new(span.as_str());
-
-                        if let Some(fg) = fg {
-                            text = text.color(ansi_color_to_egui(fg));
-                        }
-
-                        if let Some(bg) = bg {
-                            if bg != Color::Black {
-                                text = text.background_color(ansi_color_to_egui(bg));
-                            }
-                        }
-
-                        if italic == Some(true) {
-                            text = text.italics();
-                        }
+/// Lays out ANSI formatted text as a single row, `color` is used where the text doesn't set one.
+/// Lines with blinking text can't be cached.
+fn layout_line(
+    ui: &Ui,
+    text: &str,
+    color: Option<Color32>,
+    palette: &Palette,
+) -> (LineLayout, bool) {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let mut job = LayoutJob::default();
+    let mut links = vec![];
+    let mut chars = 0;
+    let mut cache = true;
 
-                        if underline == Some(true) {
-                            text = text.underline();
-                        }
+    for (style, text) in terminal::spans(text) {
+        cache &= !style.blink;
+        for span in LinkFinder::new().spans(text) {
+            let mut format = text_format(ui, style, color, palette, font_id.clone());
+            let len = span.as_str().chars().count();
+            let url = match span.kind() {
+                Some(LinkKind::Url) => Some(span.as_str().to_string()),
+                Some(LinkKind::Email) => Some(format!("mailto:{}", span.as_str())),
+                Some(_) | None => None,
+            };
+            if let Some(url) = url {
+                format.color = ui.visuals().hyperlink_color;
+                format.underline = Stroke::new(1.0, format.color);
+                links.push((chars..chars + len, url));
+            }
+            job.append(span.as_str(), 0.0, format);
+            chars += len;
+        }
+    }
 
-                        if strikethrough == Some(true) {
-                            text = text.strikethrough();
-                        }
+    let galley = ui.fonts(|f| f.layout_job(job));
+    (LineLayout { galley, links }, cache)
+}
 
-                        text = match intensity {
-                            Some(Intensity::Bold) => text.strong(),
-                            Some(Intensity::Faint) => text.weak(),
-                            Some(Intensity::Normal) | None => text,
-                        };
+fn text_format(
+    ui: &Ui,
+    style: Style,
+    color: Option<Color32>,
+    palette: &Palette,
+    font_id: FontId,
+) -> TextFormat {
+    let visuals = ui.visuals();
+    let mut fg = style.fg.map(|fg| ansi_color_to_egui(fg, palette)).or(color);
+    let mut bg = style.bg.map(|bg| ansi_color_to_egui(bg, palette));
+    if style.inverse {
+        (fg, bg) = (
+            Some(bg.unwrap_or(visuals.panel_fill)),
+            Some(fg.unwrap_or(visuals.text_color())),
+        );
+    }
+    // There is no bold monospace font
+    let mut fg = fg.unwrap_or(if style.bold {
+        visuals.strong_text_color()
+    } else {
+        visuals.text_color()
+    });
+    if style.faint {
+        fg = fg.gamma_multiply(0.6);
+    }
+    if style.blink {
+        ui.ctx().request_repaint_after(Duration::from_millis(500));
+        if ui.input(|i| i.time) % 1.0 >= 0.5 {
+            fg = Color32::TRANSPARENT;
+        }
+    }
 
-                        ui.add(Label::new(text))
-                    }
-                };
-            }
+    let line = |set: bool| {
+        if set {
+            Stroke::new(1.0, fg)
+        } else {
+            Stroke::NONE
         }
-    });
-    ui.style_mut().spacing.item_spacing = previous;
+    };
+    TextFormat {
+        font_id,
+        color: fg,
+        background: bg.unwrap_or(Color32::TRANSPARENT),
+        italics: style.italic,
+        underline: line(style.underline),
+        strikethrough: line(style.strikethrough),
+        ..Default::default()
+    }
 }
 
-fn ansi_color_to_egui(color: Color) -> Color32 {
+pub(crate) fn ansi_color_to_egui(color: Color, palette: &Palette) -> Color32 {
     match color {
-        Color::Black => Color32::from_rgb(0, 0, 0),
-        Color::Red => Color32::from_rgb(205, 49, 49),
-        Color::Green => Color32::from_rgb(13, 188, 121),
-        Color::Yellow => Color32::from_rgb(229, 229, 16),
-        Color::Blue => Color32::from_rgb(36, 114, 200),
-        Color::Magenta => Color32::from_rgb(188, 63, 188),
-        Color::Cyan => Color32::from_rgb(17, 168, 205),
-        Color::White => Color32::from_rgb(229, 229, 229),
-        Color::BrightBlack => Color32::from_rgb(102, 102, 102),
-        Color::BrightRed => Color32::from_rgb(241, 76, 76),
-        Color::BrightGreen => Color32::from_rgb(35, 209, 139),
-        Color::BrightYellow => Color32::from_rgb(245, 245, 67),
-        Color::BrightBlue => Color32::from_rgb(59, 142, 234),
-        Color::BrightMagenta => Color32::from_rgb(214, 112, 214),
-        Color::BrightCyan => Color32::from_rgb(41, 184, 219),
-        Color::BrightWhite => Color32::from_rgb(229, 229, 229),
+        Color::Indexed(index) => palette.indexed(index),
+        Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
     }
 }
+
+#[test]
+fn test_parse_stdout() {
+    let mut output = OutputBuffer::default();
+    let mut terminal = Terminal::new(crate::terminal::DEFAULT_ROWS);
+    let time = Duration::from_secs(1);
+    let progress = |value| format!("{MAGIC}7{MAGIC}progress-bar{MAGIC}desc{MAGIC}{value}{MAGIC}\n");
+    parse_stdout(&mut output, &mut terminal, &mut None, "text\n", time);
+    parse_stdout(&mut output, &mut terminal, &mut None, &progress(0.5), time);
+    parse_stdout(&mut output, &mut terminal, &mut None, &progress(1.0), time);
+    terminal.flush(&mut output);
+
+    assert!(matches!(
+        output.shown(None).collect::<Vec<_>>()[..],
+        [OutputType::Text(text, Stream::Stdout, _), OutputType::ProgressBar(desc, value)]
+            if text == "text\n" && desc == "desc\n" && *value == 1.0
+    ));
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Named values of the whole form. Can be shipped with the app in [`crate::Settings::presets`]
+/// and is serializable with any serde format, e.g. JSON:
+/// ```
+/// # use klask::Preset;
+/// let preset: Preset = serde_json::from_str(
+///     r#"{ "name": "Release", "args": { "build/release": true }, "subcommands": ["build"] }"#,
+/// ).unwrap();
+/// assert_eq!(preset.subcommands, ["build"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Preset {
+    /// Name displayed in the preset selection.
+    pub name: String,
+    /// Values keyed by the path of the arg id through the subcommands, e.g. `build/release`.
+    /// Args that are missing are reset.
+    pub args: BTreeMap<String, PresetValue>,
+    /// Names of the selected subcommands, from the top level down.
+    pub subcommands: Vec<String>,
+    /// Environment variables, used when [`crate::Settings::enable_env`] is set.
+    pub env: Vec<(String, String)>,
+    /// Used when [`crate::Settings::enable_stdin`] is set.
+    pub stdin: Option<PresetStdin>,
+    /// Used when [`crate::Settings::enable_working_dir`] is set.
+    pub working_dir: String,
+}
+
+impl Preset {
+    /// Creates an empty preset with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Value of a single arg in a [`Preset`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PresetValue {
+    /// Flags and args that take a bool.
+    Bool(bool),
+    /// Numbers and the count of flags that can be repeated.
+    Int(i64),
+    /// Numbers that don't fit in [`i64`].
+    UInt(u64),
+    /// Numbers with a fractional part.
+    Float(f64),
+    /// Args that take a single value.
+    String(String),
+    /// Args that take multiple values.
+    Strings(Vec<String>),
+}
+
+impl PresetValue {
+    pub(crate) fn as_i64(&self) -> Option<i64> {
+        match *self {
+            PresetValue::Int(i) => Some(i),
+            PresetValue::UInt(u) => u.try_into().ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match *self {
+            PresetValue::Int(i) => i.try_into().ok(),
+            PresetValue::UInt(u) => Some(u),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PresetValue::Int(i) => Some(i as f64),
+            PresetValue::UInt(u) => Some(u as f64),
+            PresetValue::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+}
+
+/// Input in a [`Preset`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresetStdin {
+    /// Text passed to stdin.
+    Text(String),
+    /// Path of a file passed to stdin. Ignored on wasm.
+    File(String),
+}
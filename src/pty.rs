@@ -0,0 +1,106 @@
+//! Pseudo-terminal for running the child, so it behaves like it would in a terminal.
+
+use crate::child_app::TerminalSize;
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    process::Command,
+};
+
+/// Opens a pseudo-terminal, returning the master and slave ends.
+pub fn open((columns, rows): TerminalSize) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut master = 0;
+    let mut slave = 0;
+    let mut size = winsize(columns, rows);
+    // SAFETY: all pointers are valid for the duration of the call
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::addr_of_mut!(size),
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: openpty succeeded, so both are open fds owned by us
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+
+    // The child only needs the slave end
+    // SAFETY: master is a valid fd
+    unsafe { libc::fcntl(master.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+
+    Ok((master, slave))
+}
+
+/// Tell the child the terminal changed size.
+pub fn resize(master: &OwnedFd, (columns, rows): TerminalSize) -> io::Result<()> {
+    let size = winsize(columns, rows);
+    // SAFETY: master is a valid fd and size outlives the call
+    if unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Make the command use `slave` as its stdio and controlling terminal.
+pub fn attach(
+    command: &mut Command,
+    slave: &OwnedFd,
+    (columns, rows): TerminalSize,
+) -> io::Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    command
+        .stdin(slave.try_clone()?)
+        .stdout(slave.try_clone()?)
+        .stderr(slave.try_clone()?)
+        .env("COLUMNS", columns.to_string())
+        .env("LINES", rows.to_string());
+    if std::env::var_os("TERM").is_none() {
+        command.env("TERM", "xterm-256color");
+    }
+
+    // SAFETY: only async-signal-safe functions are called between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            // New session without a controlling terminal, then make stdin (the slave) the controlling terminal
+            if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+fn winsize(columns: u16, rows: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: rows,
+        ws_col: columns,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+#[test]
+fn test_pty() {
+    use std::io::Read;
+
+    let (master, slave) = open((120, 40)).unwrap();
+    let mut command = Command::new("sh");
+    command.args(["-c", "stty size; echo $COLUMNS; test -t 1 && echo tty"]);
+    attach(&mut command, &slave, (120, 40)).unwrap();
+    let mut child = command.spawn().unwrap();
+    drop(command);
+    drop(slave);
+
+    let mut output = Vec::new();
+    // Reading fails with EIO on Linux once the child exits
+    let _ = std::fs::File::from(master).read_to_end(&mut output);
+    child.wait().unwrap();
+    let output = String::from_utf8_lossy(&output).replace('\r', "");
+    assert_eq!(output, "40 120\n120\ntty\n");
+}
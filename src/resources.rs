@@ -0,0 +1,253 @@
+//! Resources used by the running child and its descendants, sampled from `/proc`.
+
+use crate::history::{format_bytes, format_duration};
+use crate::settings::Localization;
+use eframe::egui::{pos2, vec2, Sense, Shape, Stroke, Ui};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Time between samples.
+const INTERVAL: Duration = Duration::from_secs(1);
+/// Number of samples in the sparkline.
+const HISTORY: usize = 60;
+
+/// Usage of the process tree at the last sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Usage {
+    /// Percent of one core since the previous sample.
+    pub cpu: f32,
+    /// Resident memory in bytes.
+    pub rss: u64,
+    pub threads: u64,
+}
+
+/// Fields of `/proc/<pid>/stat` used here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stat {
+    pid: u32,
+    ppid: u32,
+    /// CPU time of the process and its waited for children in clock ticks.
+    ticks: u64,
+    threads: u64,
+    rss_pages: u64,
+}
+
+/// Samples a process tree on a background thread, which stops when the tree is gone or
+/// this is dropped.
+#[derive(Debug)]
+pub(crate) struct ResourceMonitor {
+    samples: Arc<Mutex<Samples>>,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    /// Time and CPU ticks of the last sample.
+    last: Option<(Instant, u64)>,
+    usage: Option<Usage>,
+    /// CPU usage of the recent samples, oldest first.
+    history: VecDeque<f32>,
+    peak_rss: u64,
+}
+
+impl ResourceMonitor {
+    /// Starts sampling `pid` and its descendants.
+    pub fn new(pid: u32) -> Self {
+        let samples = Arc::<Mutex<Samples>>::default();
+        let weak = Arc::downgrade(&samples);
+        thread::spawn(move || loop {
+            // Reading `/proc` takes a while, so not while holding the lock
+            let stats = tree(pid, &all_stats());
+            let Some(samples) = weak.upgrade() else {
+                break;
+            };
+            if stats.is_empty() {
+                break;
+            }
+            lock(&samples).add(&stats, Instant::now());
+            drop(samples);
+            thread::sleep(INTERVAL);
+        });
+        Self { samples }
+    }
+
+    fn samples(&self) -> MutexGuard<'_, Samples> {
+        lock(&self.samples)
+    }
+
+    /// Most resident memory seen, `None` before the first sample.
+    pub fn peak_rss(&self) -> Option<u64> {
+        let samples = self.samples();
+        samples.usage.map(|_| samples.peak_rss)
+    }
+
+    /// CPU time used so far, `None` before the first sample.
+    pub fn cpu_time(&self) -> Option<Duration> {
+        let (_, ticks) = self.samples().last?;
+        Some(Duration::from_secs_f64(ticks as f64 / clock_ticks() as f64))
+    }
+
+    /// Row with the last sample and a sparkline of the CPU usage.
+    pub fn show(&self, ui: &mut Ui, elapsed: Duration, localization: &Localization) {
+        let samples = self.samples();
+        let Some(usage) = samples.usage else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.weak(format!(
+                "{} {:.0}%   {} {}   {} {}   {}",
+                localization.cpu,
+                usage.cpu,
+                localization.memory,
+                format_bytes(usage.rss),
+                localization.threads,
+                usage.threads,
+                format_duration(elapsed.as_secs_f64()),
+            ));
+
+            let height = ui.spacing().interact_size.y;
+            let (rect, _) =
+                ui.allocate_exact_size(vec2(HISTORY as f32 * 2.0, height), Sense::hover());
+            // At least one core so idle processes stay at the bottom
+            let max = samples.history.iter().copied().fold(100.0, f32::max);
+            let points = samples
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, cpu)| {
+                    let x = rect.right() - (samples.history.len() - 1 - i) as f32 * 2.0;
+                    pos2(x, rect.bottom() - cpu / max * rect.height())
+                })
+                .collect();
+            let stroke = Stroke::new(1.0, ui.visuals().hyperlink_color);
+            ui.painter().add(Shape::line(points, stroke));
+        });
+    }
+}
+
+impl Samples {
+    /// Adds the stats of the process tree at `now`.
+    fn add(&mut self, stats: &[Stat], now: Instant) {
+        // Exited descendants are only counted once they're waited for, so it can go down
+        let last_ticks = self.last.map_or(0, |(_, ticks)| ticks);
+        let ticks = stats.iter().map(|s| s.ticks).sum::<u64>().max(last_ticks);
+        let cpu = match self.last {
+            Some((time, _)) => {
+                let seconds = (ticks - last_ticks) as f32 / clock_ticks() as f32;
+                seconds / (now - time).as_secs_f32() * 100.0
+            }
+            None => 0.0,
+        };
+        let usage = Usage {
+            cpu,
+            rss: stats.iter().map(|s| s.rss_pages).sum::<u64>() * page_size(),
+            threads: stats.iter().map(|s| s.threads).sum(),
+        };
+
+        self.last = Some((now, ticks));
+        self.usage = Some(usage);
+        self.peak_rss = self.peak_rss.max(usage.rss);
+        self.history.push_back(cpu);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+    }
+}
+
+/// Stats of every process.
+fn all_stats() -> Vec<Stat> {
+    let Ok(dir) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+    dir.flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+            parse_stat(pid, &stat)
+        })
+        .collect()
+}
+
+fn parse_stat(pid: u32, stat: &str) -> Option<Stat> {
+    // The name before the state is in parentheses and can contain spaces
+    let fields: Vec<_> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    // Numbered like in proc(5), the state is the third field
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(Stat {
+        pid,
+        ppid: field(4)? as u32,
+        ticks: field(14)? + field(15)? + field(16)? + field(17)?,
+        threads: field(20)?,
+        rss_pages: field(24)?,
+    })
+}
+
+/// Stats of `root` and its descendants.
+fn tree(root: u32, stats: &[Stat]) -> Vec<Stat> {
+    let mut children = HashMap::<_, Vec<_>>::new();
+    for stat in stats {
+        children.entry(stat.ppid).or_default().push(stat.pid);
+    }
+    let mut pids = HashSet::new();
+    let mut queue = vec![root];
+    while let Some(pid) = queue.pop() {
+        // A pid can't be its own ancestor, but `/proc` isn't read atomically
+        if pids.insert(pid) {
+            queue.extend(children.get(&pid).into_iter().flatten());
+        }
+    }
+    stats
+        .iter()
+        .filter(|s| pids.contains(&s.pid))
+        .copied()
+        .collect()
+}
+
+/// The samples stay consistent even if a thread panicked while holding the lock.
+fn lock(samples: &Mutex<Samples>) -> MutexGuard<'_, Samples> {
+    samples.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn clock_ticks() -> u64 {
+    // SAFETY: sysconf has no safety requirements
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as u64
+}
+
+fn page_size() -> u64 {
+    // SAFETY: sysconf has no safety requirements
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64
+}
+
+#[test]
+fn test_process_tree() {
+    let stat = |pid, ppid| {
+        let line = format!("{pid} (a) b) S {ppid} 1 1 0 -1 0 0 0 0 0 3 4 5 6 20 0 2 0 100 0 7");
+        parse_stat(pid, &line).unwrap()
+    };
+    assert_eq!(
+        stat(5, 1),
+        Stat {
+            pid: 5,
+            ppid: 1,
+            ticks: 18,
+            threads: 2,
+            rss_pages: 7,
+        }
+    );
+
+    let stats = [stat(1, 0), stat(5, 1), stat(6, 5), stat(7, 1), stat(8, 6)];
+    let pids: Vec<_> = tree(5, &stats).iter().map(|s| s.pid).collect();
+    assert_eq!(pids, [5, 6, 8]);
+
+    let monitor = ResourceMonitor::new(std::process::id());
+    let start = Instant::now();
+    while monitor.peak_rss().is_none() && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(monitor.peak_rss().unwrap() > 0);
+    assert!(monitor.cpu_time().is_some());
+}
@@ -0,0 +1,152 @@
+use crate::settings::Localization;
+use eframe::egui::{Key, TextEdit, Ui};
+use regex::Regex;
+
+/// Search in the output, kept between runs.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Search {
+    pub query: String,
+    /// Treat the query as a regex instead of plain text.
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Only show lines with a match.
+    pub only_matching: bool,
+    /// Position of the selected match among all matches, several can be in one line.
+    pub current: usize,
+    /// Scroll to the selected match in the next frame.
+    pub scroll: bool,
+    /// Query and options `compiled` was made from.
+    compiled_from: (String, bool, bool),
+    compiled: Option<Result<Regex, String>>,
+}
+
+impl Search {
+    /// The query compiled as a regex, `None` when there is no query.
+    pub fn regex(&mut self) -> Option<Result<Regex, String>> {
+        if self.query.is_empty() {
+            return None;
+        }
+
+        let key = (self.query.clone(), self.regex, self.case_sensitive);
+        if self.compiled.is_none() || self.compiled_from != key {
+            let pattern = if self.regex {
+                self.query.clone()
+            } else {
+                regex::escape(&self.query)
+            };
+            // The flag is part of the pattern so regexes with different options never compare equal
+            let flags = if self.case_sensitive { "" } else { "(?i)" };
+            let regex = Regex::new(&format!("{flags}{pattern}")).map_err(|err| err.to_string());
+            self.compiled = Some(regex);
+            self.compiled_from = key;
+        }
+        self.compiled.clone()
+    }
+
+    /// Search bar with navigation between the `matches`, or the error of an invalid regex.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        matches: Option<Result<usize, String>>,
+        localization: &Localization,
+    ) {
+        let count = match &matches {
+            Some(Ok(count)) => *count,
+            _ => 0,
+        };
+        let previous = self.clone();
+
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                TextEdit::singleline(&mut self.query)
+                    .hint_text(&localization.search_output)
+                    .desired_width(200.0),
+            );
+            if response.changed() {
+                self.current = 0;
+                self.scroll = true;
+            }
+            // Enter selects the next match and keeps typing in the field
+            if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                if ui.input(|i| i.modifiers.shift) {
+                    self.select_previous(count);
+                } else {
+                    self.select_next(count);
+                }
+                response.request_focus();
+            }
+
+            ui.add_enabled_ui(count > 0, |ui| {
+                if ui.button(&localization.previous_match).clicked() {
+                    self.select_previous(count);
+                }
+                if ui.button(&localization.next_match).clicked() {
+                    self.select_next(count);
+                }
+            });
+            match &matches {
+                Some(Ok(_)) => {
+                    let current = if count == 0 { 0 } else { self.current + 1 };
+                    ui.label(format!("{current}/{count}"));
+                }
+                Some(Err(err)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                None => {}
+            }
+
+            ui.checkbox(&mut self.regex, &localization.regex);
+            ui.checkbox(&mut self.case_sensitive, &localization.match_case);
+            ui.checkbox(&mut self.only_matching, &localization.only_matching);
+        });
+
+        if (
+            &self.query,
+            self.regex,
+            self.case_sensitive,
+            self.only_matching,
+        ) != (
+            &previous.query,
+            previous.regex,
+            previous.case_sensitive,
+            previous.only_matching,
+        ) {
+            // The matches are found again in the next frame
+            ui.ctx().request_repaint();
+        }
+    }
+
+    fn select_next(&mut self, count: usize) {
+        if count > 0 {
+            self.current = (self.current + 1) % count;
+            self.scroll = true;
+        }
+    }
+
+    fn select_previous(&mut self, count: usize) {
+        if count > 0 {
+            self.current = (self.current + count - 1) % count;
+            self.scroll = true;
+        }
+    }
+}
+
+#[test]
+fn test_search_regex() {
+    let mut search = Search {
+        query: "a.c".into(),
+        ..Default::default()
+    };
+    let regex = search.regex().unwrap().unwrap();
+    assert!(regex.is_match("xA.C"));
+    assert!(!regex.is_match("abc"));
+
+    search.regex = true;
+    search.case_sensitive = true;
+    let regex = search.regex().unwrap().unwrap();
+    assert!(regex.is_match("abc"));
+    assert!(!regex.is_match("ABC"));
+
+    search.query = "(".into();
+    assert!(search.regex().unwrap().is_err());
+}
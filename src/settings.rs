@@ -1,8 +1,9 @@
 // Structs are marked as `#[non_exhaustive]` to allow
 // to add other optionas alter withour breaking compatibility.
 
-use eframe::egui::{self, style::Spacing, Style};
-use std::borrow::Cow;
+use crate::{output::OnRunFinished, Preset};
+use eframe::egui::{self, style::Spacing, Color32, Stroke, Style, Visuals};
+use std::{borrow::Cow, path::PathBuf, time::Duration};
 
 /// Settings for klask.
 /// Is marked with `#[non_exhaustive]` so you must construct it like this
@@ -36,8 +37,73 @@ pub struct Settings {
     /// Override builtin strings. By default everything is in english.
     pub localization: Localization,
 
-    /// Egui style used in GUI.
+    /// Egui style used in GUI. Its visuals are replaced by the selected theme.
     pub style: Style,
+
+    /// Colors for ANSI escape sequences in the output, unless a theme is selected.
+    /// Match them to the visuals of [`Settings::style`].
+    pub palette: Palette,
+
+    /// Color of text printed to stderr that doesn't set its own color, unless a theme is selected.
+    pub stderr_color: Color32,
+
+    /// Color of the status of runs that exited successfully, unless a theme is selected.
+    pub success_color: Color32,
+
+    /// Themes the user can switch between in the GUI. The first one is selected by default.
+    /// Empty by default, so [`Settings::style`] and the colors above are kept. The builtin
+    /// ones are [`Theme::dark`], [`Theme::light`] and [`Theme::high_contrast`].
+    pub themes: Vec<Theme>,
+
+    /// Select the first theme that is dark or light like the system, when it's known.
+    pub follow_system_theme: bool,
+
+    /// Presets that are always available and can't be deleted by the user.
+    pub presets: Vec<Preset>,
+
+    /// Restore the form, selected tab and window size from the last session.
+    /// Stored on disk on native and in `localStorage` on wasm.
+    pub remember_session: bool,
+
+    /// Args whose values are never stored, neither in the last session nor in saved presets.
+    /// Use it for passwords and other secrets. Identified by the path of the arg id like in [`Preset::args`].
+    pub forget_args: Vec<String>,
+
+    /// Never store the environment variables, like [`Settings::forget_args`].
+    pub forget_env: bool,
+
+    /// Never store the stdin text or file, like [`Settings::forget_args`].
+    pub forget_stdin: bool,
+
+    /// How many runs are kept in the history panel. Pass 0 to disable it.
+    pub max_history: usize,
+
+    /// How many lines of output are kept for each run, older lines are dropped.
+    pub max_output_lines: usize,
+
+    /// Called in the GUI when a run finishes.
+    pub on_run_finished: Option<OnRunFinished>,
+
+    /// Keep stdin of the running app open and show a line for typing input to it under the output.
+    /// By default stdin is closed after writing the input from [`Settings::enable_stdin`].
+    /// Does nothing on wasm.
+    pub interactive_stdin: bool,
+
+    /// Run the app in a pseudo-terminal sized to fit the output, so it can use colors and
+    /// progress bars like in a terminal. Stdout and stderr are then merged. Only on Unix.
+    pub pty: bool,
+
+    /// Write the output of every run to a new file in this directory, with ANSI codes
+    /// and without progress bars. The directory is created if needed. Does nothing on wasm.
+    pub output_log_dir: Option<PathBuf>,
+
+    /// How long the app gets to exit after the terminate button before its processes are killed.
+    /// Only on Unix, elsewhere the app is killed right away.
+    pub stop_grace_period: Duration,
+
+    /// Terminate runs that take longer than this, like with the terminate button.
+    /// The user can change it for the next runs in the GUI.
+    pub timeout: Option<Duration>,
 }
 
 impl Default for Settings {
@@ -57,6 +123,205 @@ impl Default for Settings {
                 },
                 ..Default::default()
             },
+            palette: Palette::default(),
+            stderr_color: Color32::from_rgb(241, 76, 76),
+            success_color: Color32::from_rgb(13, 188, 121),
+            themes: vec![],
+            follow_system_theme: true,
+            presets: vec![],
+            remember_session: false,
+            forget_args: vec![],
+            forget_env: false,
+            forget_stdin: false,
+            max_history: 20,
+            max_output_lines: 100_000,
+            on_run_finished: None,
+            interactive_stdin: false,
+            pty: false,
+            output_log_dir: None,
+            stop_grace_period: Duration::from_secs(5),
+            timeout: None,
+        }
+    }
+}
+
+/// Colors of the GUI and the output.
+/// ```
+/// # use klask::{Settings, Theme};
+/// # use eframe::egui::Color32;
+/// let mut settings = Settings::default();
+/// let mut theme = Theme::dark();
+/// theme.name = "Dark blue".into();
+/// theme.visuals.panel_fill = Color32::from_rgb(0, 20, 40);
+/// settings.themes.insert(0, theme);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Theme {
+    /// Displayed in the theme selection.
+    pub name: String,
+    /// Colors of the GUI. Its `dark_mode` is matched with the system theme.
+    pub visuals: Visuals,
+    /// Colors for ANSI escape sequences in the output.
+    pub palette: Palette,
+    /// Outline of invalid fields and text of error messages.
+    pub error_color: Color32,
+    /// Color of text printed to stderr, unless it sets its own color.
+    pub stderr_color: Color32,
+    /// Color of the status of runs that exited successfully.
+    pub success_color: Color32,
+}
+
+impl Theme {
+    /// Default egui dark theme with the colors of [`Settings::default`].
+    pub fn dark() -> Self {
+        let settings = Settings::default();
+        Self {
+            name: "Dark".into(),
+            visuals: Visuals::dark(),
+            palette: settings.palette,
+            error_color: Color32::RED,
+            stderr_color: settings.stderr_color,
+            success_color: settings.success_color,
+        }
+    }
+
+    /// Default egui light theme with darker ANSI colors.
+    pub fn light() -> Self {
+        Self {
+            name: "Light".into(),
+            visuals: Visuals::light(),
+            palette: Palette {
+                ansi: [
+                    Color32::from_rgb(0, 0, 0),
+                    Color32::from_rgb(205, 49, 49),
+                    Color32::from_rgb(0, 135, 0),
+                    Color32::from_rgb(120, 120, 0),
+                    Color32::from_rgb(4, 81, 165),
+                    Color32::from_rgb(188, 5, 188),
+                    Color32::from_rgb(5, 152, 188),
+                    Color32::from_rgb(85, 85, 85),
+                    Color32::from_rgb(102, 102, 102),
+                    Color32::from_rgb(205, 49, 49),
+                    Color32::from_rgb(20, 150, 20),
+                    Color32::from_rgb(135, 138, 0),
+                    Color32::from_rgb(4, 81, 165),
+                    Color32::from_rgb(188, 5, 188),
+                    Color32::from_rgb(5, 152, 188),
+                    Color32::from_rgb(140, 140, 140),
+                ],
+            },
+            error_color: Color32::from_rgb(210, 0, 0),
+            stderr_color: Color32::from_rgb(205, 49, 49),
+            success_color: Color32::from_rgb(0, 160, 0),
+        }
+    }
+
+    /// White on black with outlined widgets and bright ANSI colors.
+    pub fn high_contrast() -> Self {
+        let mut visuals = Visuals::dark();
+        visuals.override_text_color = Some(Color32::WHITE);
+        visuals.panel_fill = Color32::BLACK;
+        visuals.window_fill = Color32::BLACK;
+        visuals.extreme_bg_color = Color32::BLACK;
+        visuals.faint_bg_color = Color32::from_gray(24);
+        visuals.hyperlink_color = Color32::from_rgb(90, 200, 255);
+        visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+        let widgets = &mut visuals.widgets;
+        for widget in [
+            &mut widgets.noninteractive,
+            &mut widgets.inactive,
+            &mut widgets.hovered,
+            &mut widgets.active,
+            &mut widgets.open,
+        ] {
+            widget.bg_stroke = Stroke::new(1.0, Color32::WHITE);
+            widget.fg_stroke.color = Color32::WHITE;
+        }
+
+        Self {
+            name: "High contrast".into(),
+            visuals,
+            palette: Palette {
+                ansi: [
+                    Color32::from_rgb(110, 110, 110),
+                    Color32::from_rgb(255, 85, 85),
+                    Color32::from_rgb(85, 255, 85),
+                    Color32::from_rgb(255, 255, 85),
+                    Color32::from_rgb(100, 150, 255),
+                    Color32::from_rgb(255, 85, 255),
+                    Color32::from_rgb(85, 255, 255),
+                    Color32::from_rgb(230, 230, 230),
+                    Color32::from_rgb(160, 160, 160),
+                    Color32::from_rgb(255, 120, 120),
+                    Color32::from_rgb(120, 255, 120),
+                    Color32::from_rgb(255, 255, 140),
+                    Color32::from_rgb(140, 180, 255),
+                    Color32::from_rgb(255, 140, 255),
+                    Color32::from_rgb(140, 255, 255),
+                    Color32::from_rgb(255, 255, 255),
+                ],
+            },
+            error_color: Color32::from_rgb(255, 60, 60),
+            stderr_color: Color32::from_rgb(255, 120, 120),
+            success_color: Color32::from_rgb(85, 255, 85),
+        }
+    }
+}
+
+/// Colors for ANSI escape sequences in the output, see [`Theme::palette`].
+/// ```
+/// # use klask::Theme;
+/// # use eframe::egui::Color32;
+/// let mut theme = Theme::dark();
+/// theme.palette.ansi[1] = Color32::from_rgb(255, 0, 0);
+/// assert_eq!(theme.palette.indexed(1), Color32::from_rgb(255, 0, 0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Palette {
+    /// Black, red, green, yellow, blue, magenta, cyan and white, then their bright versions.
+    /// The rest of the 256 colors are the standard xterm color cube and grays.
+    pub ansi: [Color32; 16],
+}
+
+impl Palette {
+    /// Color at `index` in the 256 color palette.
+    pub fn indexed(&self, index: u8) -> Color32 {
+        match index {
+            0..=15 => self.ansi[index as usize],
+            16..=231 => {
+                let level = |i: u8| if i == 0 { 0 } else { 55 + 40 * i };
+                let i = index - 16;
+                Color32::from_rgb(level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            232..=255 => Color32::from_gray(8 + 10 * (index - 232)),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        // Black and bright black are grays that can be read on a dark background
+        Self {
+            ansi: [
+                Color32::from_rgb(110, 110, 110),
+                Color32::from_rgb(205, 49, 49),
+                Color32::from_rgb(13, 188, 121),
+                Color32::from_rgb(229, 229, 16),
+                Color32::from_rgb(36, 114, 200),
+                Color32::from_rgb(188, 63, 188),
+                Color32::from_rgb(17, 168, 205),
+                Color32::from_rgb(229, 229, 229),
+                Color32::from_rgb(128, 128, 128),
+                Color32::from_rgb(241, 76, 76),
+                Color32::from_rgb(35, 209, 139),
+                Color32::from_rgb(245, 245, 67),
+                Color32::from_rgb(59, 142, 234),
+                Color32::from_rgb(214, 112, 214),
+                Color32::from_rgb(41, 184, 219),
+                Color32::from_rgb(229, 229, 229),
+            ],
         }
     }
 }
@@ -97,11 +362,122 @@ pub struct Localization {
     pub working_directory: String,
     /// Button text for running the binary. Default is "Run".
     pub run: String,
-    /// Button text for killing the binary. Default is "Kill".
+    /// Button text for killing the binary where it can't be stopped gracefully. Default is "Kill".
     pub kill: String,
+    /// Button text for sending SIGINT to the binary. Default is "Interrupt (Ctrl+C)".
+    pub interrupt: String,
+    /// Button text for sending SIGTERM to the binary. Default is "Terminate".
+    pub terminate: String,
+    /// Button text for sending SIGKILL to the binary. Default is "Force kill".
+    pub force_kill: String,
+    /// Button text for stopping the binary with SIGSTOP. Default is "Pause".
+    pub pause: String,
+    /// Button text for continuing the paused binary. Default is "Resume".
+    pub resume: String,
+    /// Displayed instead of [`Localization::running`] while paused. Default is "Paused".
+    pub paused: String,
+    /// Menu for sending other signals to the binary. Default is "Send signal".
+    pub send_signal: String,
+    /// Checkbox for the timeout of the next runs. Default is "Timeout".
+    pub timeout: String,
+    /// Displayed before the time left until the timeout. Default is "Times out in".
+    pub times_out_in: String,
+    /// Displayed when the run was terminated after the timeout. Default is "Timed out".
+    pub timed_out: String,
+    /// Label of the CPU usage of the running binary. Default is "CPU".
+    pub cpu: String,
+    /// Label of the memory used by the running binary. Default is "Memory".
+    pub memory: String,
+    /// Label of the thread count of the running binary. Default is "Threads".
+    pub threads: String,
+    /// Label of the most memory used in a finished run. Default is "peak memory".
+    pub peak_memory: String,
+    /// Label of the CPU time used in a finished run. Default is "CPU time".
+    pub cpu_time: String,
     /// Text that shows when the binary is running. There will be animated dots ("...") displayed after it.
     /// Default is "Running".
     pub running: String,
+    /// Header of the panel showing the command line that will be run. Default is "Command".
+    pub command: String,
+    /// Button text for copying the command line. Default is "Copy".
+    pub copy: String,
+    /// Button text for filling the form from a pasted command line. Default is "Import command".
+    pub import_command: String,
+    /// Hint for the field where a command line can be pasted. Default is "Paste a command line".
+    pub paste_command_line: String,
+    /// Error when a pasted command line can't be split into arguments.
+    /// Default is "Command line has an unclosed quote".
+    pub error_unclosed_quote: String,
+    /// Displayed before the arguments that couldn't be imported. Default is "Couldn't import: ".
+    pub unplaced_arguments: String,
+    /// Header of the preset section. Default is "Presets".
+    pub presets: String,
+    /// Hint for the preset name. Default is "Preset name".
+    pub preset_name: String,
+    /// Button text for saving a preset. Default is "Save".
+    pub save: String,
+    /// Button text for loading a preset. Default is "Load".
+    pub load: String,
+    /// Button text for deleting a preset. Default is "Delete".
+    pub delete: String,
+    /// Header of the run history panel. Default is "History".
+    pub history: String,
+    /// Button text for filling the form from a past run. Default is "Restore".
+    pub restore: String,
+    /// Button text for running a past run again. Default is "Re-run".
+    pub rerun: String,
+    /// Displayed when the run exited with code 0. Default is "Finished successfully".
+    pub exited_successfully: String,
+    /// Displayed before the exit code of a failed run. Default is "Exited with code".
+    pub exited_with_code: String,
+    /// Displayed before the signal that terminated the run. Default is "Terminated by signal".
+    pub exited_with_signal: String,
+    /// Displayed when the run was terminated or killed with the buttons. Default is "Killed".
+    pub killed: String,
+    /// Option of a group of args that can't be used together for using none of them.
+    /// Default is "None".
+    pub no_selection: String,
+    /// Shows stdout and stderr together in the output. Default is "All".
+    pub all_output: String,
+    /// Only shows stdout in the output. Default is "stdout".
+    pub stdout: String,
+    /// Only shows stderr in the output. Default is "stderr".
+    pub stderr: String,
+    /// Hint for the line sent to the stdin of the running app. Default is "Input".
+    pub input_line: String,
+    /// Button text for sending a line to the running app. Default is "Send".
+    pub send: String,
+    /// Button text for closing stdin of the running app. Default is "Close stdin (EOF)".
+    pub close_stdin: String,
+    /// Displayed in the progress bar while the input is being written. Default is "Sending input".
+    pub sending_input: String,
+    /// Displayed with the error when reading the input failed. Default is "Failed to read the input:".
+    pub input_error: String,
+    /// Option of the theme selection that follows the system theme. Default is "System theme".
+    pub system_theme: String,
+    /// Displayed before the number of lines dropped from the start of the output.
+    /// Default is "Earlier lines dropped: ".
+    pub dropped_lines: String,
+    /// Hint of the search field over the output. Default is "Search output".
+    pub search_output: String,
+    /// Checkbox for searching with a regex. Default is "Regex".
+    pub regex: String,
+    /// Checkbox for a case sensitive search. Default is "Match case".
+    pub match_case: String,
+    /// Checkbox for only showing lines with a match. Default is "Only matching lines".
+    pub only_matching: String,
+    /// Button text for selecting the previous match. Default is "Previous".
+    pub previous_match: String,
+    /// Button text for selecting the next match. Default is "Next".
+    pub next_match: String,
+    /// Menu for saving the output to a file. Default is "Save output...".
+    pub save_output: String,
+    /// Saving the output without ANSI codes. Default is "Plain text".
+    pub plain_text: String,
+    /// Saving the output with ANSI codes. Default is "Text with ANSI codes".
+    pub ansi_text: String,
+    /// Saving the output as a web page with its colors. Default is "HTML".
+    pub html: String,
 }
 
 impl Default for Localization {
@@ -123,7 +499,106 @@ impl Default for Localization {
             working_directory: "Working directory".into(),
             run: "Run".into(),
             kill: "Kill".into(),
+            interrupt: "Interrupt (Ctrl+C)".into(),
+            terminate: "Terminate".into(),
+            force_kill: "Force kill".into(),
+            pause: "Pause".into(),
+            resume: "Resume".into(),
+            paused: "Paused".into(),
+            send_signal: "Send signal".into(),
+            timeout: "Timeout".into(),
+            times_out_in: "Times out in".into(),
+            timed_out: "Timed out".into(),
+            cpu: "CPU".into(),
+            memory: "Memory".into(),
+            threads: "Threads".into(),
+            peak_memory: "peak memory".into(),
+            cpu_time: "CPU time".into(),
             running: "Running".into(),
+            command: "Command".into(),
+            copy: "Copy".into(),
+            import_command: "Import command".into(),
+            paste_command_line: "Paste a command line".into(),
+            error_unclosed_quote: "Command line has an unclosed quote".into(),
+            unplaced_arguments: "Couldn't import: ".into(),
+            presets: "Presets".into(),
+            preset_name: "Preset name".into(),
+            save: "Save".into(),
+            load: "Load".into(),
+            delete: "Delete".into(),
+            history: "History".into(),
+            restore: "Restore".into(),
+            rerun: "Re-run".into(),
+            exited_successfully: "Finished successfully".into(),
+            exited_with_code: "Exited with code".into(),
+            exited_with_signal: "Terminated by signal".into(),
+            killed: "Killed".into(),
+            no_selection: "None".into(),
+            all_output: "All".into(),
+            stdout: "stdout".into(),
+            stderr: "stderr".into(),
+            input_line: "Input".into(),
+            send: "Send".into(),
+            close_stdin: "Close stdin (EOF)".into(),
+            sending_input: "Sending input".into(),
+            input_error: "Failed to read the input:".into(),
+            system_theme: "System theme".into(),
+            dropped_lines: "Earlier lines dropped: ".into(),
+            search_output: "Search output".into(),
+            regex: "Regex".into(),
+            match_case: "Match case".into(),
+            only_matching: "Only matching lines".into(),
+            previous_match: "Previous".into(),
+            next_match: "Next".into(),
+            save_output: "Save output...".into(),
+            plain_text: "Plain text".into(),
+            ansi_text: "Text with ANSI codes".into(),
+            html: "HTML".into(),
+        }
+    }
+}
+
+#[test]
+fn test_palette_indexed() {
+    let palette = Palette::default();
+    assert_eq!(palette.indexed(9), palette.ansi[9]);
+    assert_eq!(palette.indexed(16), Color32::from_rgb(0, 0, 0));
+    assert_eq!(palette.indexed(208), Color32::from_rgb(255, 135, 0));
+    assert_eq!(palette.indexed(255), Color32::from_gray(238));
+}
+
+#[test]
+fn test_theme_contrast() {
+    // Contrast ratio as defined by WCAG
+    let luminance = |color: Color32| {
+        let channel = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(color.r()) + 0.7152 * channel(color.g()) + 0.0722 * channel(color.b())
+    };
+    let contrast = |a: Color32, b: Color32| {
+        let (a, b) = (luminance(a), luminance(b));
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    };
+
+    for theme in [Theme::dark(), Theme::light(), Theme::high_contrast()] {
+        let background = theme.visuals.panel_fill;
+        let colors = theme.palette.ansi.iter().chain([
+            &theme.error_color,
+            &theme.stderr_color,
+            &theme.success_color,
+        ]);
+        for (i, &color) in colors.enumerate() {
+            assert!(
+                contrast(color, background) >= 3.0,
+                "color {i} of {} is {color:?}",
+                theme.name
+            );
         }
     }
 }
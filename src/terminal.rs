@@ -0,0 +1,566 @@
+//! Enough of a terminal for output that redraws itself, like progress bars and spinners.
+//!
+//! Each stream gets its own screen of the last few lines that the cursor can
+//! still reach. Lines are kept in the output as ANSI text with only color and style sequences,
+//! they are rendered again whenever the child changes them.
+
+use crate::buffer::OutputBuffer;
+use crate::output::{OutputType, Stream};
+use std::{collections::VecDeque, time::Duration};
+
+/// Rows of the screen when the child doesn't run in a pseudo-terminal.
+pub const DEFAULT_ROWS: u16 = 24;
+/// Columns the cursor can be moved to, so a child can't make the GUI allocate huge lines.
+/// Printed text still makes lines as long as it is.
+const MAX_COLUMNS: usize = 4096;
+
+/// Color set with an SGR sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Color {
+    /// Index in the 256 color palette, the first 16 are the ANSI colors.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// Text attributes set with SGR sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub inverse: bool,
+    pub strikethrough: bool,
+}
+
+impl Style {
+    fn apply_sgr(&mut self, params: &[usize]) {
+        if params.is_empty() {
+            *self = Style::default();
+        }
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.faint = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                5 | 6 => self.blink = true,
+                7 => self.inverse = true,
+                9 => self.strikethrough = true,
+                22 => (self.bold, self.faint) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                25 => self.blink = false,
+                27 => self.inverse = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.fg = Some(Color::Indexed((param - 30) as u8)),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Indexed((param - 40) as u8)),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Indexed((param - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(Color::Indexed((param - 100 + 8) as u8)),
+                // 256 colors with `5;n` and 24-bit colors with `2;r;g;b`
+                38 | 48 => {
+                    let mut next = || params.next().unwrap_or(0).min(255) as u8;
+                    let color = match next() {
+                        5 => Color::Indexed(next()),
+                        2 => Color::Rgb(next(), next(), next()),
+                        _ => continue,
+                    };
+                    match param {
+                        38 => self.fg = Some(color),
+                        _ => self.bg = Some(color),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Sequence that sets exactly this style.
+    fn to_sgr(self) -> String {
+        let mut sgr = String::from("\x1b[0");
+        for (set, code) in [
+            (self.bold, 1),
+            (self.faint, 2),
+            (self.italic, 3),
+            (self.underline, 4),
+            (self.blink, 5),
+            (self.inverse, 7),
+            (self.strikethrough, 9),
+        ] {
+            if set {
+                sgr += &format!(";{code}");
+            }
+        }
+        // `base` is 30 for the foreground and 40 for the background
+        let color = |color: Color, base: u8| match color {
+            Color::Indexed(i @ 0..=7) => format!(";{}", base + i),
+            Color::Indexed(i @ 8..=15) => format!(";{}", base + 60 + i - 8),
+            Color::Indexed(i) => format!(";{};5;{i}", base + 8),
+            Color::Rgb(r, g, b) => format!(";{};2;{r};{g};{b}", base + 8),
+        };
+        if let Some(fg) = self.fg {
+            sgr += &color(fg, 30);
+        }
+        if let Some(bg) = self.bg {
+            sgr += &color(bg, 40);
+        }
+        sgr + "m"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    style: Style {
+        fg: None,
+        bg: None,
+        bold: false,
+        faint: false,
+        italic: false,
+        underline: false,
+        blink: false,
+        inverse: false,
+        strikethrough: false,
+    },
+};
+
+#[derive(Debug)]
+struct Line {
+    /// Index in the output.
+    index: usize,
+    cells: Vec<Cell>,
+    /// Changed since it was last rendered to the output.
+    dirty: bool,
+}
+
+impl Line {
+    /// Updates the text in the output.
+    fn render(&mut self, output: &mut OutputBuffer) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+
+        if let Some(OutputType::Text(text, ..)) = output.get_mut(self.index) {
+            text.clear();
+            let mut style = Style::default();
+            for cell in &self.cells {
+                if cell.style != style {
+                    style = cell.style;
+                    *text += &style.to_sgr();
+                }
+                text.push(cell.ch);
+            }
+            if style != Style::default() {
+                *text += "\x1b[0m";
+            }
+            text.push('\n');
+        }
+    }
+}
+
+/// Lines of one stream and its cursor.
+#[derive(Debug, Default)]
+struct Screen {
+    lines: VecDeque<Line>,
+    /// Can be past the last line, lines are created once something is written to them.
+    row: usize,
+    col: usize,
+    saved: (usize, usize),
+    style: Style,
+    /// Start of an escape sequence that was split between writes.
+    pending: String,
+}
+
+/// Streams of the child, each with its own screen.
+#[derive(Debug)]
+pub(crate) struct Terminal {
+    rows: usize,
+    stdout: Screen,
+    stderr: Screen,
+}
+
+impl Terminal {
+    pub fn new(rows: u16) -> Self {
+        Self {
+            rows: rows.max(1) as usize,
+            stdout: Screen::default(),
+            stderr: Screen::default(),
+        }
+    }
+
+    /// Writes text from the child, adding lines to `output` or changing lines it added before.
+    /// Changed lines are only updated in `output` by [`Terminal::flush`].
+    pub fn write(&mut self, output: &mut OutputBuffer, stream: Stream, text: &str, time: Duration) {
+        let screen = match stream {
+            Stream::Stdout => &mut self.stdout,
+            Stream::Stderr => &mut self.stderr,
+        };
+        let mut writer = Writer {
+            screen,
+            rows: self.rows,
+            output,
+            stream,
+            time,
+        };
+        writer.write(text);
+    }
+
+    /// Updates the lines changed since the last flush in `output`. Called once per frame,
+    /// so a long line written in many parts isn't rendered again after every part.
+    pub fn flush(&mut self, output: &mut OutputBuffer) {
+        for line in self.stdout.lines.iter_mut().chain(&mut self.stderr.lines) {
+            line.render(output);
+        }
+    }
+}
+
+struct Writer<'a> {
+    screen: &'a mut Screen,
+    rows: usize,
+    output: &'a mut OutputBuffer,
+    stream: Stream,
+    time: Duration,
+}
+
+impl Writer<'_> {
+    fn write(&mut self, text: &str) {
+        let text = std::mem::take(&mut self.screen.pending) + text;
+        let mut rest = text.as_str();
+        while let Some(c) = rest.chars().next() {
+            if c == '\x1b' {
+                match parse_escape(rest) {
+                    Some((len, escape)) => {
+                        self.escape(escape);
+                        rest = &rest[len..];
+                    }
+                    None => {
+                        self.screen.pending = rest.to_string();
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            rest = &rest[c.len_utf8()..];
+            if c == '\n' {
+                // Ends the line under the cursor, which is kept even when empty
+                self.line();
+            }
+            let screen = &mut *self.screen;
+            match c {
+                '\n' => {
+                    screen.row = (screen.row + 1).min(self.rows);
+                    screen.col = 0;
+                }
+                '\r' => screen.col = 0,
+                '\x08' => screen.col = screen.col.saturating_sub(1),
+                '\t' => screen.col = (screen.col / 8 + 1) * 8,
+                c if c.is_control() => {}
+                c => self.put(c),
+            }
+        }
+    }
+
+    /// Line under the cursor, created with any lines above it if needed.
+    fn line(&mut self) -> &mut Line {
+        let screen = &mut *self.screen;
+        while screen.lines.len() <= screen.row {
+            let index = self
+                .output
+                .push(0, OutputType::Text(String::new(), self.stream, self.time));
+            screen.lines.push_back(Line {
+                index,
+                cells: vec![],
+                dirty: true,
+            });
+        }
+        // Lines scrolled off the screen can't change anymore
+        while screen.lines.len() > self.rows {
+            if let Some(mut line) = screen.lines.pop_front() {
+                line.render(self.output);
+            }
+            screen.row = screen.row.saturating_sub(1);
+            screen.saved.0 = screen.saved.0.saturating_sub(1);
+        }
+        &mut screen.lines[screen.row]
+    }
+
+    /// Line under the cursor, if it exists.
+    fn current_line(&mut self) -> Option<&mut Line> {
+        self.screen.lines.get_mut(self.screen.row)
+    }
+
+    fn put(&mut self, ch: char) {
+        let (col, style) = (self.screen.col, self.screen.style);
+        let line = self.line();
+        if line.cells.len() <= col {
+            line.cells.resize(col + 1, BLANK);
+        }
+        line.cells[col] = Cell { ch, style };
+        line.dirty = true;
+        self.screen.col += 1;
+    }
+
+    fn escape(&mut self, escape: Escape) {
+        match escape {
+            Escape::Csi(params, action) => self.csi(&params, action),
+            Escape::SaveCursor => self.screen.saved = (self.screen.row, self.screen.col),
+            Escape::RestoreCursor => (self.screen.row, self.screen.col) = self.screen.saved,
+            Escape::Ignored => {}
+        }
+    }
+
+    fn csi(&mut self, params: &[usize], action: char) {
+        // Missing or zero counts and positions are 1
+        let arg = |i: usize| params.get(i).copied().unwrap_or(0).clamp(1, MAX_COLUMNS);
+        let last_row = self.rows - 1;
+        let screen = &mut *self.screen;
+        match action {
+            'm' => screen.style.apply_sgr(params),
+            'A' => screen.row = screen.row.saturating_sub(arg(0)),
+            'B' => screen.row = (screen.row + arg(0)).min(last_row),
+            'C' => screen.col = (screen.col + arg(0)).min(MAX_COLUMNS - 1),
+            'D' => screen.col = screen.col.saturating_sub(arg(0)),
+            'E' => (screen.row, screen.col) = ((screen.row + arg(0)).min(last_row), 0),
+            'F' => (screen.row, screen.col) = (screen.row.saturating_sub(arg(0)), 0),
+            'G' => screen.col = arg(0) - 1,
+            'H' | 'f' => (screen.row, screen.col) = ((arg(0) - 1).min(last_row), arg(1) - 1),
+            'J' => self.erase_display(params.first().copied().unwrap_or(0)),
+            'K' => self.erase_line(params.first().copied().unwrap_or(0)),
+            's' => screen.saved = (screen.row, screen.col),
+            'u' => (screen.row, screen.col) = screen.saved,
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let col = self.screen.col;
+        let Some(line) = self.current_line() else {
+            return;
+        };
+        match mode {
+            0 => line.cells.truncate(col),
+            1 => {
+                let end = (col + 1).min(line.cells.len());
+                line.cells[..end].fill(BLANK);
+            }
+            _ => line.cells.clear(),
+        }
+        line.dirty = true;
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let row = self.screen.row;
+        let lines = self.screen.lines.iter_mut().enumerate();
+        let erased: Vec<_> = match mode {
+            0 => lines.filter(|(i, _)| *i > row).collect(),
+            1 => lines.filter(|(i, _)| *i < row).collect(),
+            _ => lines.collect(),
+        };
+        for (_, line) in erased {
+            line.cells.clear();
+            line.dirty = true;
+        }
+        match mode {
+            0 => self.erase_line(0),
+            1 => self.erase_line(1),
+            _ => {}
+        }
+    }
+}
+
+/// Splits text with SGR sequences, like the lines of the terminal, into styled parts.
+/// Other escape sequences are dropped.
+pub(crate) fn spans(text: &str) -> Vec<(Style, &str)> {
+    let mut spans = vec![];
+    let mut style = Style::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest.find('\x1b').unwrap_or(rest.len());
+        if end > 0 {
+            spans.push((style, &rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+        match parse_escape(rest) {
+            Some((len, escape)) => {
+                if let Escape::Csi(params, 'm') = escape {
+                    style.apply_sgr(&params);
+                }
+                rest = &rest[len..];
+            }
+            None => break,
+        }
+    }
+    spans
+}
+
+#[derive(Debug, PartialEq)]
+enum Escape {
+    /// Control sequence with its numeric parameters and the final character.
+    Csi(Vec<usize>, char),
+    SaveCursor,
+    RestoreCursor,
+    /// Valid sequences that don't change the text, like window titles.
+    Ignored,
+}
+
+/// Longest unfinished sequence kept between writes, longer ones are dropped.
+const MAX_PENDING: usize = 4096;
+
+/// Parses the escape sequence at the start of `text`, returning its length.
+/// `None` if the sequence isn't finished.
+fn parse_escape(text: &str) -> Option<(usize, Escape)> {
+    let unfinished = || (text.len() > MAX_PENDING).then_some((text.len(), Escape::Ignored));
+    let bytes = text.as_bytes();
+    let Some(&kind) = bytes.get(1) else {
+        return unfinished();
+    };
+
+    match kind {
+        b'[' => {
+            let Some(end) = bytes[2..].iter().position(|b| !(0x20..0x40).contains(b)) else {
+                return unfinished();
+            };
+            let end = end + 2;
+            // A byte that doesn't belong in a sequence ends it
+            if !(0x40..0x7f).contains(&bytes[end]) {
+                return Some((end, Escape::Ignored));
+            }
+            let params = &text[2..end];
+            let action = bytes[end] as char;
+            // Private sequences like hiding the cursor
+            if params.starts_with(['<', '=', '>', '?']) {
+                return Some((end + 1, Escape::Ignored));
+            }
+            let params = params
+                .split([';', ':'])
+                .map(|p| p.parse().unwrap_or(0))
+                .collect();
+            Some((end + 1, Escape::Csi(params, action)))
+        }
+        // Strings ended by BEL or ESC \
+        b']' | b'P' | b'X' | b'^' | b'_' => {
+            let end = (2..bytes.len()).find_map(|i| match bytes[i] {
+                b'\x07' => Some(i + 1),
+                b'\x1b' if bytes.get(i + 1) == Some(&b'\\') => Some(i + 2),
+                _ => None,
+            });
+            match end {
+                Some(end) => Some((end, Escape::Ignored)),
+                None => unfinished(),
+            }
+        }
+        // Character sets
+        b'(' | b')' | b'*' | b'+' => match bytes.get(2) {
+            Some(_) => Some((3, Escape::Ignored)),
+            None => unfinished(),
+        },
+        b'7' => Some((2, Escape::SaveCursor)),
+        b'8' => Some((2, Escape::RestoreCursor)),
+        _ => Some((
+            1 + text[1..].chars().next().map_or(0, char::len_utf8),
+            Escape::Ignored,
+        )),
+    }
+}
+
+#[cfg(test)]
+fn write_all(writes: &[&str]) -> Vec<String> {
+    let mut terminal = Terminal::new(DEFAULT_ROWS);
+    let mut output = OutputBuffer::default();
+    for text in writes {
+        terminal.write(&mut output, Stream::Stdout, text, Duration::ZERO);
+    }
+    terminal.flush(&mut output);
+    output
+        .shown(None)
+        .map(|o| match o {
+            OutputType::Text(text, ..) => text.clone(),
+            OutputType::ProgressBar(..) => unreachable!(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_carriage_return() {
+    assert_eq!(
+        write_all(&["10%\r20%", "\r30%\ndone\n"]),
+        ["30%\n", "done\n"]
+    );
+    assert_eq!(write_all(&["abcdef\rXY\n"]), ["XYcdef\n"]);
+}
+
+#[test]
+fn test_blank_lines() {
+    assert_eq!(write_all(&["a\n\n"]), ["a\n", "\n"]);
+    assert_eq!(write_all(&["\n", "b\n", "\n\n"]), ["\n", "b\n", "\n", "\n"]);
+}
+
+#[test]
+fn test_render_on_flush() {
+    let mut terminal = Terminal::new(DEFAULT_ROWS);
+    let mut output = OutputBuffer::default();
+    let text = |output: &OutputBuffer| match output.get(0) {
+        Some(OutputType::Text(text, ..)) => text.clone(),
+        _ => unreachable!(),
+    };
+    for part in ["1", "2", "3"] {
+        terminal.write(&mut output, Stream::Stdout, part, Duration::ZERO);
+    }
+    assert_eq!(text(&output), "");
+    terminal.flush(&mut output);
+    assert_eq!(text(&output), "123\n");
+}
+
+#[test]
+fn test_cursor_movement() {
+    assert_eq!(
+        write_all(&["one\ntwo\n\x1b[1A\x1b[Kthree\n"]),
+        ["one\n", "three\n"]
+    );
+    assert_eq!(write_all(&["a\nb\n\x1b[2J\x1b[Hc"]), ["c\n", "\n"]);
+    assert_eq!(write_all(&["abc\x1b[2D\x1b[1KX"]), [" Xc\n"]);
+}
+
+#[test]
+fn test_huge_cursor_moves() {
+    let lines = write_all(&["\x1b[999999999Gx\x1b[99999999999999999999Cy\x1b[5;999999999Hz"]);
+    assert_eq!(lines[0].len(), MAX_COLUMNS + 1);
+    assert!(lines[0].ends_with("y\n"));
+    assert_eq!(lines[4].len(), MAX_COLUMNS + 1);
+}
+
+#[test]
+fn test_style() {
+    assert_eq!(
+        write_all(&["red \x1b[1;3", "1mX\x1b[0m\x1b]0;title\x07\n"]),
+        ["red \x1b[0;1;31mX\x1b[0m\n"]
+    );
+}
+
+#[test]
+fn test_extended_colors() {
+    let lines = write_all(&["\x1b[38;5;208;48;2;1;2;3;7mX\n"]);
+    assert_eq!(lines, ["\x1b[0;7;38;5;208;48;2;1;2;3mX\x1b[0m\n"]);
+
+    let spans = spans(&lines[0]);
+    let (style, text) = spans[0];
+    assert_eq!(text, "X");
+    assert_eq!(style.fg, Some(Color::Indexed(208)));
+    assert_eq!(style.bg, Some(Color::Rgb(1, 2, 3)));
+    assert!(style.inverse);
+}